@@ -0,0 +1,176 @@
+//! The audit log.
+//!
+//! Every moderation action a plugin takes through Theia is recorded here,
+//! both to the tracing log under the `theia::audit` target, and to an
+//! append-only JSON-lines file if one is configured.
+
+use crate::prelude::*;
+
+use ::serde::{Deserialize, Serialize};
+use ::std::fs::OpenOptions;
+use ::std::io::Write;
+use ::std::path::PathBuf;
+use ::std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single action recorded in the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TheiaAuditEntry {
+    /// Time the action was taken, in seconds since the Unix epoch
+    pub timestamp: u64,
+
+    /// Instance ID of the plugin that took the action
+    pub plugin: String,
+
+    /// The action taken, such as `kick`
+    pub action: String,
+
+    pub guild_id: Option<String>,
+    pub channel_id: Option<String>,
+
+    /// The user, role or messages the action was taken against
+    pub target: String,
+
+    /// Reason given by the plugin, if any
+    pub reason: Option<String>,
+
+    /// Invocation ID of the command the plugin was run for, if any
+    pub invoke_id: Option<String>,
+
+    /// User who invoked the command the plugin was run for, if any
+    pub invoked_by: Option<String>,
+}
+
+impl TheiaAuditEntry {
+    /// Create an entry for an action taken by a plugin, timestamped now.
+    pub fn new<P: AsRef<str>, A: AsRef<str>, T: AsRef<str>>(
+        plugin: P,
+        action: A,
+        target: T,
+        origin: Option<&TheiaDiscordMessage>,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            plugin: String::from(plugin.as_ref()),
+            action: String::from(action.as_ref()),
+            guild_id: None,
+            channel_id: None,
+            target: String::from(target.as_ref()),
+            reason: None,
+            invoke_id: origin
+                .and_then(|m| m.command_invocation.as_ref())
+                .and_then(|cmd| cmd.invoke_id.clone()),
+            invoked_by: origin.map(|m| m.author_id.clone()),
+        }
+    }
+}
+
+/// Append-only log of actions taken by plugins.
+#[derive(Debug, Default)]
+pub struct TheiaAuditLog {
+    path: Option<PathBuf>,
+}
+
+impl TheiaAuditLog {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    /// Record an action in the audit log.
+    pub async fn record(&self, entry: &TheiaAuditEntry) {
+        let line = match ::serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize audit log entry {:?}: {}", entry, e);
+                return;
+            }
+        };
+
+        info!(target: "theia::audit", "{}", line);
+
+        if let Some(path) = self.path.clone() {
+            let contents = format!("{}\n", line);
+            let written = ::tokio::task::spawn_blocking(move || {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| file.write_all(contents.as_bytes()))
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+
+            if let Err(e) = written {
+                error!("Failed to write audit log entry {}: {}", line, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin() -> TheiaDiscordMessage {
+        let mut cmd = CommandInvocation::parse(&["t;"], "t;ban 1")
+            .unwrap()
+            .unwrap();
+        cmd.invoke_id = Some(String::from("01ARZ3NDEKTSV4RRFFQ69G5FAV"));
+
+        TheiaDiscordMessage {
+            message_id: String::from("10"),
+            channel_id: String::from("20"),
+            author_id: String::from("30"),
+            guild_id: Some(String::from("40")),
+            webhook_id: None,
+            content: String::from("t;ban 1"),
+            command_invocation: Some(cmd),
+            message_proxy: None,
+        }
+    }
+
+    #[test]
+    fn entry_from_origin() {
+        let origin = origin();
+        let entry = TheiaAuditEntry::new("mod", "ban", "1", Some(&origin));
+        assert_eq!("mod", &entry.plugin);
+        assert_eq!("ban", &entry.action);
+        assert_eq!("1", &entry.target);
+        assert_eq!(
+            Some("01ARZ3NDEKTSV4RRFFQ69G5FAV"),
+            entry.invoke_id.as_deref()
+        );
+        assert_eq!(Some("30"), entry.invoked_by.as_deref());
+
+        let entry = TheiaAuditEntry::new("mod", "kick", "2", None);
+        assert_eq!(None, entry.invoke_id);
+        assert_eq!(None, entry.invoked_by);
+    }
+
+    #[tokio::test]
+    async fn appended_to_file() {
+        let path = ::std::env::temp_dir().join(format!("theia-audit-{}", ::ulid::Ulid::new()));
+        let log = TheiaAuditLog::new(Some(path.clone()));
+
+        let mut entry = TheiaAuditEntry::new("mod", "kick", "2", None);
+        entry.reason = Some(String::from("spam"));
+        log.record(&entry).await;
+        log.record(&TheiaAuditEntry::new("mod", "ban", "3", None))
+            .await;
+
+        let contents = ::std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<TheiaAuditEntry> = contents
+            .lines()
+            .map(|line| ::serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(2, entries.len());
+        assert_eq!("kick", &entries[0].action);
+        assert_eq!(Some("spam"), entries[0].reason.as_deref());
+        assert_eq!("ban", &entries[1].action);
+
+        ::std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -52,7 +52,7 @@ fn main() -> Result<()> {
             .map(|e| format!("{:?}", e))
             .collect::<Vec<String>>()
             .join(" "),
-    ) {
+    )? {
         match cmd.command.as_ref() {
             "help" => main_help(&cmd),
             "cmd-parser-dump" => {
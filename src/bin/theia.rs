@@ -60,7 +60,7 @@ async fn main() -> Result<()> {
             .map(|e| format!("{:?}", e))
             .collect::<Vec<String>>()
             .join(" "),
-    ) {
+    )? {
         match cmd.command.as_ref() {
             "help" => main_help(&cmd),
             "run" => run(&cmd).await,
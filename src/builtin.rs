@@ -0,0 +1,178 @@
+//! Built-in commands.
+//!
+//! Built-in commands are only used if no plugin provides a command with the
+//! same name.
+
+use crate::prelude::*;
+
+/// Names of all built-in commands.
+pub const BUILTIN_COMMANDS: &[&str] = &["help", "error"];
+
+/// Invoke the built-in command matching a command invocation, if any.
+pub async fn invoke_builtin(
+    ctx: &SerenityContext,
+    theia: &Theia,
+    msg: &TheiaDiscordMessage,
+    cmd: &CommandInvocation,
+) -> Result<(), TheiaError> {
+    match cmd.command.as_ref() {
+        "help" => builtin_help(ctx, theia, msg, cmd).await,
+        "error" => builtin_error(ctx, theia, msg, cmd).await,
+        _ => Ok(()),
+    }
+}
+
+/// `help [<[plugin:]command> [<subcommand>...]]` - list commands, or show the help
+/// for a single command.
+async fn builtin_help(
+    ctx: &SerenityContext,
+    theia: &Theia,
+    msg: &TheiaDiscordMessage,
+    cmd: &CommandInvocation,
+) -> Result<(), TheiaError> {
+    if cmd.arguments.is_empty() {
+        let summaries: Vec<String> = theia
+            .plugin_command_summaries()
+            .into_iter()
+            .map(|(name, summary)| {
+                if summary.is_empty() {
+                    format!("`{}{}`", theia.prefix(), name)
+                } else {
+                    summary
+                }
+            })
+            .collect();
+
+        msg.reply(
+            &ctx.http,
+            format!("Available commands:\n{}", summaries.join("\n")),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let cmdname = &cmd.arguments[0];
+    let subpath = &cmd.arguments[1..];
+    if let Some((plugin, cmdcfg)) = theia.resolve_qualified_command(cmdname) {
+        let chain = cmdcfg.resolve_subcommands(subpath);
+        if chain.len() == subpath.len() {
+            let mut qualified = vec![theia.command_display_name(plugin, cmdcfg)];
+            qualified.extend(chain.iter().map(|c| c.name.clone()));
+
+            let help_s = render_command_help(
+                theia.prefix(),
+                qualified.join(" "),
+                chain.last().copied().unwrap_or(cmdcfg),
+            );
+
+            msg.reply(&ctx.http, help_s).await?;
+            return Ok(());
+        }
+    }
+
+    msg.reply(
+        &ctx.http,
+        format!(
+            "\u{274c} No help available for `{prefix}{0}`.",
+            cmd.arguments.join(" "),
+            prefix = theia.prefix()
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// `error <error ID>` - show the details of an error from the error journal.
+///
+/// Only available to admin users.
+async fn builtin_error(
+    ctx: &SerenityContext,
+    theia: &Theia,
+    msg: &TheiaDiscordMessage,
+    cmd: &CommandInvocation,
+) -> Result<(), TheiaError> {
+    if !theia.is_admin(&msg.author_id) {
+        let command = format!("{}error", theia.prefix());
+        msg.reply(
+            &ctx.http,
+            theia
+                .config
+                .rejection_reply("user_permission", &[("command", &command)]),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let error_id = match cmd.arguments.first() {
+        Some(error_id) => error_id,
+        None => {
+            let usage = format!("{}error <error ID>", theia.prefix());
+            msg.reply(
+                &ctx.http,
+                theia.config.rejection_reply(
+                    "arguments",
+                    &[("reason", "An error ID is required."), ("usage", &usage)],
+                ),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let record = match theia.journal.get(error_id) {
+        Some(record) => record,
+        None => {
+            msg.reply(
+                &ctx.http,
+                format!(
+                    "\u{274c} No error with ID `{}` found in the error journal.",
+                    error_id
+                ),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let mut lines = vec![
+        format!("**Error `{}`** (<t:{}>)", record.error_id, record.timestamp),
+        format!("**Error:** {}", record.error),
+    ];
+
+    if let Some(plugin) = &record.plugin {
+        lines.push(format!("**Plugin:** `{}`", plugin));
+    }
+
+    if let Some(message) = &record.message {
+        if let Some(invoke_id) = message
+            .command_invocation
+            .as_ref()
+            .and_then(|c| c.invoke_id.as_ref())
+        {
+            lines.push(format!("**Invocation:** `{}`", invoke_id));
+        }
+
+        lines.push(format!(
+            "**Message:** `{}` by <@{}> in <#{}>",
+            message.message_id, message.author_id, message.channel_id
+        ));
+    }
+
+    // Fit as much of the debug output as possible into the reply
+    let mut reply = lines.join("\n");
+    let mut budget = 2000usize.saturating_sub(reply.chars().count() + 16);
+    let mut debug = record.error_debug.replace("```", "`\u{200b}``");
+    if debug.chars().count() > budget {
+        budget = budget.saturating_sub(3);
+        debug = debug.chars().take(budget).collect::<String>() + "...";
+    }
+
+    reply.push_str(&format!("\n```\n{}\n```", debug));
+    msg.reply(&ctx.http, reply).await?;
+    Ok(())
+}
@@ -1,13 +1,19 @@
 //! Command invocation parser
 
+use crate::builtin::{invoke_builtin, BUILTIN_COMMANDS};
+use crate::error::TheiaParseError;
+use crate::parser::tokenize::tokenize_with_ends;
 use crate::prelude::*;
 use crate::plugin::comms::*;
+use crate::util::distance::closest_matches;
 use ::serde::{Deserialize, Serialize};
+use ::serde_json::Value;
+use ::std::collections::HashMap;
 
 /// A single invocation of a command
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CommandInvocation {
-    /// Command invocation ID
+    /// Command invocation ID, a ULID unique to this invocation
     pub invoke_id: Option<String>,
 
     /// The command prefix used to invoke this command
@@ -16,6 +22,13 @@ pub struct CommandInvocation {
     /// The name of the command
     pub command: String,
 
+    /// The instance ID of the plugin this command was resolved to, or that
+    /// was requested explicitly with `plugin:command` syntax
+    pub plugin: Option<String>,
+
+    /// Path of subcommand names, if the command has subcommands
+    pub subcommand: Vec<String>,
+
     /// List of flags at the start of the command invocation
     pub start_flags: Vec<String>,
 
@@ -25,6 +38,27 @@ pub struct CommandInvocation {
 
     /// List of flags at the end of the command invocation
     pub end_flags: Vec<String>,
+
+    /// The remainder of the input after the prefix, command name and
+    /// subcommand names (along with any flags before them), with the
+    /// whitespace separating it from those removed, but otherwise untouched
+    pub raw_arguments: String,
+
+    /// Options passed to the command, keyed by name without leading dashes.
+    ///
+    /// Bare flags (such as `-help`) are present with no values, while
+    /// `-key=value` options (or `--key value`, if `key` is declared as
+    /// taking a value) have one value for each time they were passed.
+    pub options: HashMap<String, Vec<String>>,
+
+    /// Command arguments converted according to the command's argument
+    /// schema, if it has one
+    pub parsed_args: Option<HashMap<String, Value>>,
+
+    /// Error tokenizing the arguments, if any, in which case `arguments`
+    /// were split on whitespace instead
+    #[serde(skip)]
+    pub parse_error: Option<TheiaParseError>,
 }
 
 impl CommandInvocation {
@@ -36,13 +70,58 @@ impl CommandInvocation {
 
     pub fn generate_invoke_id(&mut self) {
         if self.invoke_id.is_none() {
-            // TODO: generate an invocation ID
-            // self.invoke_id = Some("TODO");
+            self.invoke_id = Some(::ulid::Ulid::new().to_string());
+        }
+    }
+
+    /// Moves the leading arguments naming the given subcommand path into
+    /// `subcommand`, removing them from `arguments` and `raw_arguments`.
+    pub fn set_subcommand(&mut self, subcommand: Vec<String>) {
+        let tokens: Vec<String> = self.arguments.drain(..subcommand.len()).collect();
+
+        // The tokens, rather than the resolved names, are matched, as they
+        // may be aliases or differ in case
+        let mut raw = self.raw_arguments.as_str();
+        for token in &tokens {
+            // Start flags before a subcommand name were already collected,
+            // so are dropped along with it
+            loop {
+                let word = raw.split_whitespace().next().unwrap_or_default();
+                let rest = raw
+                    .trim_start()
+                    .get(word.len()..)
+                    .unwrap_or_default()
+                    .trim_start();
+                if word == token {
+                    raw = rest;
+                    break;
+                } else if word.starts_with('-') {
+                    raw = rest;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.raw_arguments = String::from(raw);
+
+        // Flags following only a subcommand are treated as start flags, as
+        // they would be following only a command name
+        if self.arguments.is_empty() {
+            self.start_flags.append(&mut self.end_flags);
         }
+
+        self.subcommand = subcommand;
     }
 }
 
 impl CommandInvocation {
+    /// The command name, followed by the subcommand path, if any
+    pub fn qualified_command(&self) -> String {
+        let mut path = vec![self.command.clone()];
+        path.extend(self.subcommand.iter().cloned());
+        path.join(" ")
+    }
+
     /// Checks whether the `-help` flag was passed
     pub fn help_requested(&self) -> bool {
         self.has_startflag_any(&["-help"])
@@ -68,6 +147,20 @@ impl CommandInvocation {
             .iter()
             .any(|e| flags.iter().any(|f| f.as_ref() == e))
     }
+
+    /// Checks whether any of the given options were passed, with or without
+    /// a value
+    pub fn has_option_any<T: AsRef<str>>(&self, names: &[T]) -> bool {
+        names.iter().any(|n| self.options.contains_key(n.as_ref()))
+    }
+
+    /// Returns the last value passed for the given option, if any
+    pub fn option<T: AsRef<str>>(&self, name: T) -> Option<&str> {
+        self.options
+            .get(name.as_ref())
+            .and_then(|values| values.last())
+            .map(String::as_str)
+    }
 }
 
 impl CommandInvocation {
@@ -75,7 +168,28 @@ impl CommandInvocation {
     ///
     /// Takes a list of command prefixes, and an input string. A command
     /// invocation can use any of the specified command prefixes.
-    pub fn parse<P: AsRef<str>, M: AsRef<str>>(prefixes: &[P], input: M) -> Option<Self> {
+    ///
+    /// Returns `Ok(None)` if the input does not start with a known prefix,
+    /// and an error if the command name could not be tokenized. If only the
+    /// arguments could not be tokenized, they are split on whitespace
+    /// instead, and the error is kept in `parse_error`.
+    pub fn parse<P: AsRef<str>, M: AsRef<str>>(
+        prefixes: &[P],
+        input: M,
+    ) -> Result<Option<Self>, TheiaParseError> {
+        Self::parse_with_options::<P, M, &str>(prefixes, input, &[])
+    }
+
+    /// Parse a command invocation, with a list of options that take a value.
+    ///
+    /// As with [`parse`](Self::parse), but any option named in
+    /// `value_options` (such as `user` for `--user foo`) consumes the
+    /// following argument as its value.
+    pub fn parse_with_options<P: AsRef<str>, M: AsRef<str>, O: AsRef<str>>(
+        prefixes: &[P],
+        input: M,
+        value_options: &[O],
+    ) -> Result<Option<Self>, TheiaParseError> {
         let input = String::from(input.as_ref());
 
         // Try to match from our list of prefixes
@@ -95,7 +209,7 @@ impl CommandInvocation {
         }
 
         if matched_prefix.is_none() {
-            return None;
+            return Ok(None);
         }
 
         // Okay, we have a known prefix, let's split this thing apart
@@ -104,80 +218,130 @@ impl CommandInvocation {
         let mut input = String::from(input);
         input.replace_range(..matched_prefix.len(), "");
 
-        let argsplit: Vec<String> = input.split_whitespace().map(String::from).collect();
-        if argsplit.len() < 1 {
-            return None;
-        }
-
-        let mut args: Vec<String> = Vec::new();
-        let mut argsplit_idx = 1;
+        let offset = matched_prefix.chars().count();
+        let (args, parse_error) = match tokenize_with_ends(&input, offset) {
+            Ok(tokens) => (tokens, None),
+            Err(why) => {
+                // Fall back to splitting on whitespace, as long as the
+                // command name itself can be tokenized
+                let trimmed = input.trim_start();
+                let leading = input.chars().count() - trimmed.chars().count();
+                let command_len = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+                let mut tokens: Vec<(String, usize)> =
+                    tokenize_with_ends(&trimmed[..command_len], offset + leading)?
+                        .into_iter()
+                        .map(|(token, end)| (token, end + leading))
+                        .collect();
+
+                tokens.extend(
+                    trimmed[command_len..]
+                        .split_whitespace()
+                        .map(|arg| (String::from(arg), 0)),
+                );
+                (tokens, Some(why))
+            }
+        };
 
-        while argsplit_idx < argsplit.len() {
-            let mut arg = argsplit[argsplit_idx].clone();
-            if arg.starts_with('"') {
-                while !arg.ends_with('"') {
-                    argsplit_idx += 1;
-                    arg = format!("{} {}", arg, argsplit[argsplit_idx]);
-                }
+        let mut args = args.into_iter();
+        let (command, command_end) = match args.next() {
+            Some(command) => command,
+            None => return Ok(None),
+        };
 
-                arg = arg
-                    .trim_start_matches('"')
-                    .trim_end_matches('"')
-                    .to_string();
+        let (plugin, command) = match command.split_once(':') {
+            Some((plugin, name)) if !plugin.is_empty() && !name.is_empty() => {
+                (Some(String::from(plugin)), String::from(name))
             }
 
-            args.push(arg);
-            argsplit_idx += 1;
-        }
+            _ => (None, command),
+        };
 
-        // Split `args` into start flags, arguments, and end flags
+        let raw_arguments: String = input.chars().skip(command_end).collect();
+        let raw_arguments = String::from(raw_arguments.trim_start());
+        let mut args = args.map(|(arg, _)| arg);
 
-        let mut start_flags: Vec<String> = Vec::new();
-        let mut arguments: Vec<String> = Vec::new();
-        let mut end_flags: Vec<String> = Vec::new();
+        // Pull options out of `args`, marking which entries are flags
 
-        let mut fp_first_arg: Option<usize> = None;
-        let mut fp_first_endflag: Option<usize> = None;
+        let mut options: HashMap<String, Vec<String>> = HashMap::new();
+        let mut flagged_args: Vec<(String, bool)> = Vec::new();
+        let mut options_ended = false;
 
-        'fp_passone: for argidx in 0..args.len() {
-            if fp_first_arg.is_none() && !args[argidx].starts_with('-') {
-                fp_first_arg = Some(argidx);
-                break 'fp_passone;
+        while let Some(arg) = args.next() {
+            if options_ended || !is_option(&arg) {
+                flagged_args.push((arg, false));
+                continue;
             }
-        }
 
-        'fp_passtwo: for argidx in (0..args.len()).rev() {
-            if fp_first_endflag.is_none() && !args[argidx].starts_with('-') {
-                fp_first_endflag = Some(argidx + 1);
-                break 'fp_passtwo;
+            if arg == "--" {
+                options_ended = true;
+                continue;
             }
+
+            let name = arg.trim_start_matches('-');
+            if let Some((name, value)) = name.split_once('=') {
+                options
+                    .entry(String::from(name))
+                    .or_default()
+                    .push(String::from(value));
+            } else if value_options.iter().any(|o| o.as_ref() == name) {
+                let values = options.entry(String::from(name)).or_default();
+                if let Some(value) = args.next() {
+                    values.push(value);
+                }
+            } else {
+                options.entry(String::from(name)).or_default();
+            }
+
+            flagged_args.push((arg, true));
         }
 
-        for argidx in 0..args.len() {
+        // Split `flagged_args` into start flags, arguments, and end flags
+
+        let mut start_flags: Vec<String> = Vec::new();
+        let mut arguments: Vec<String> = Vec::new();
+        let mut end_flags: Vec<String> = Vec::new();
+
+        let fp_first_arg: Option<usize> = flagged_args.iter().position(|(_, flag)| !flag);
+        let fp_first_endflag: Option<usize> = flagged_args
+            .iter()
+            .rposition(|(_, flag)| !flag)
+            .map(|idx| idx + 1);
+
+        for (argidx, (arg, _)) in flagged_args.into_iter().enumerate() {
             if fp_first_endflag.is_some() && fp_first_endflag.unwrap() <= argidx {
-                end_flags.push(args[argidx].clone());
+                end_flags.push(arg);
             } else if fp_first_arg.is_some() && fp_first_arg.unwrap() <= argidx {
-                arguments.push(args[argidx].clone());
+                arguments.push(arg);
             } else {
-                start_flags.push(args[argidx].clone());
+                start_flags.push(arg);
             }
         }
 
         // Return the CommandInvocation object
-        Some(CommandInvocation {
+        Ok(Some(CommandInvocation {
             invoke_id: None,
             prefix: matched_prefix.clone(),
-            command: argsplit[0]
-                .trim_start_matches('"')
-                .trim_end_matches('"')
-                .to_string(),
+            command,
+            plugin,
+            subcommand: Vec::new(),
             start_flags,
             arguments,
             end_flags,
-        })
+            raw_arguments,
+            options,
+            parsed_args: None,
+            parse_error,
+        }))
     }
 }
 
+/// Checks whether an argument is an option, which starts with a dash but
+/// isn't a lone dash or a negative number.
+fn is_option(arg: &str) -> bool {
+    let mut chars = arg.chars();
+    chars.next() == Some('-') && !matches!(chars.next(), None | Some('0'..='9'))
+}
+
 pub async fn invoke_command<'ctx, 'msg>(
     ctx: &'ctx SerenityContext,
     msg: &'msg TheiaDiscordMessage,
@@ -189,31 +353,46 @@ pub async fn invoke_command<'ctx, 'msg>(
     let data = ctx.data.read().await;
     let theia = data.get::<TheiaContainer>().unwrap();
 
-    let cmd = msg.command_invocation.clone().unwrap();
+    let mut msg = msg.clone();
+    let mut cmd = msg.command_invocation.clone().unwrap();
     // msg.reply(&ctx.http, format!("```\n{:#?}\n```", msg)).await?;
 
-    let plugin = theia.plugin_with_command(&cmd.command);
+    let plugin = cmd
+        .plugin
+        .as_ref()
+        .and_then(|id| theia.plugin(id))
+        .filter(|p| p.command_config(&cmd.command).is_some());
+
     if plugin.is_none() {
-        return Ok(());
+        if cmd.plugin.is_none() && BUILTIN_COMMANDS.contains(&cmd.command.as_ref()) {
+            return invoke_builtin(ctx, theia, &msg, &cmd).await;
+        }
+
+        return suggest_command(ctx, theia, &msg, &cmd).await;
     }
 
     let plugin = plugin.unwrap();
-    if plugin.config.handle_help(&cmd.command) && cmd.help_requested() {
-        if let Some(cmdcfg) = plugin.command_config(&cmd.command) {
-            let (mut help_s, help_details) =
-                parse_command_help(theia.prefix(), &cmdcfg.name, &cmdcfg.help);
-
-            if let Some(details) = help_details {
-                help_s = format!("{}\n\n{}", help_s, details);
-            }
+    let cmdchain = plugin.command_chain(&cmd.command, &cmd.subcommand);
+    let cmdcfg = cmdchain.last().copied();
+    let display_name = if cmdchain.is_empty() {
+        cmd.qualified_command()
+    } else {
+        theia.command_chain_display_name(plugin, &cmdchain)
+    };
 
-            msg.reply(&ctx.http, help_s).await?;
+    if plugin.config.handle_help(&cmd.command) && cmd.help_requested() {
+        if let Some(cmdcfg) = cmdcfg {
+            msg.reply(
+                &ctx.http,
+                render_command_help(theia.prefix(), &display_name, cmdcfg),
+            )
+            .await?;
         } else {
             msg.reply(
                 &ctx.http,
                 format!(
                     "\u{274c} No help available for `{prefix}{0}`.",
-                    &cmd.command,
+                    cmd.qualified_command(),
                     prefix = theia.prefix()
                 ),
             )
@@ -223,6 +402,42 @@ pub async fn invoke_command<'ctx, 'msg>(
         return Ok(());
     }
 
+    if cmdchain.iter().any(|c| c.admin_only) && !theia.is_admin(&msg.author_id) {
+        let command = format!("{}{}", theia.prefix(), display_name);
+        msg.reply(
+            &ctx.http,
+            theia
+                .config
+                .rejection_reply("user_permission", &[("command", &command)]),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    if let Some(schema) = cmdcfg.and_then(|cmdcfg| cmdcfg.args.as_ref()) {
+        match parse_command_args(schema, &cmd.arguments) {
+            Ok(parsed_args) => {
+                cmd.parsed_args = Some(parsed_args);
+                msg.command_invocation = Some(cmd);
+            }
+
+            Err(why) => {
+                let reason = why.to_string();
+                let usage = command_usage(theia.prefix(), &display_name, schema);
+                msg.reply(
+                    &ctx.http,
+                    theia
+                        .config
+                        .rejection_reply("arguments", &[("reason", &reason), ("usage", &usage)]),
+                )
+                .await?;
+
+                return Ok(());
+            }
+        }
+    }
+
     let msgs = vec![
         TheiaPluginOutgoingMessage::bot_info(&ctx).await,
         TheiaPluginOutgoingMessage::plugin_config(&plugin).await,
@@ -237,6 +452,49 @@ pub async fn invoke_command<'ctx, 'msg>(
     Ok(plugin.invoke(&ctx, &msgs).await?)
 }
 
+/// Reply to an unknown command with the closest known commands, if
+/// suggestions are enabled and any commands are close enough.
+async fn suggest_command(
+    ctx: &SerenityContext,
+    theia: &Theia,
+    msg: &TheiaDiscordMessage,
+    cmd: &CommandInvocation,
+) -> Result<(), TheiaError> {
+    let cfg = &theia.config.suggestions;
+    if !cfg.enabled || cmd.plugin.is_some() {
+        return Ok(());
+    }
+
+    let suggestions = closest_matches(
+        &cmd.command,
+        &theia.visible_command_names(),
+        cfg.max_distance,
+        cfg.max_suggestions,
+    );
+
+    if suggestions.is_empty() || !theia.take_suggestion_slot(&msg.channel_id) {
+        return Ok(());
+    }
+
+    let suggestions: Vec<String> = suggestions
+        .iter()
+        .map(|name| format!("`{}{}`", theia.prefix(), name))
+        .collect();
+
+    msg.reply(
+        &ctx.http,
+        format!(
+            "\u{2753} Unknown command `{prefix}{0}`. Did you mean {1}?",
+            &cmd.command,
+            suggestions.join(", "),
+            prefix = theia.prefix()
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,7 +502,7 @@ mod tests {
     #[test]
     fn no_prefix_returns_none() {
         assert_eq!(
-            None,
+            Ok(None),
             CommandInvocation::parse(&["t;", "t!"], "unprefixed -test",)
         );
     }
@@ -252,17 +510,21 @@ mod tests {
     #[test]
     fn mismatched_prefix_returns_none() {
         assert_eq!(
-            None,
+            Ok(None),
             CommandInvocation::parse(&["t;", "t!"], "tx;unprefixed -test",)
         );
     }
 
     #[test]
     fn prefix_selection_correct() {
-        let cmd_one = CommandInvocation::parse(&["t;", "t!"], "t;test").unwrap();
+        let cmd_one = CommandInvocation::parse(&["t;", "t!"], "t;test")
+            .unwrap()
+            .unwrap();
         assert_eq!("t;", &cmd_one.prefix);
 
-        let cmd_two = CommandInvocation::parse(&["t;", "t!"], "t!test").unwrap();
+        let cmd_two = CommandInvocation::parse(&["t;", "t!"], "t!test")
+            .unwrap()
+            .unwrap();
         assert_eq!("t!", &cmd_two.prefix);
     }
 
@@ -272,6 +534,7 @@ mod tests {
             &["t;", "t!"],
             "t;flagtest -startone -starttwo test -midone test -endone -endtwo",
         )
+        .unwrap()
         .unwrap();
 
         assert!(cmd.has_startflag_any(&["-startone"]));
@@ -280,4 +543,158 @@ mod tests {
         assert!(cmd.has_endflag_any(&["-endone"]));
         assert!(cmd.has_endflag_any(&["-endtwo"]));
     }
+
+    #[test]
+    fn key_value_options() {
+        let cmd = CommandInvocation::parse_with_options(
+            &["t;"],
+            "t;opttest -limit=10 --user foo bar -limit=20 -verbose",
+            &["user"],
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(vec![String::from("bar")], cmd.arguments);
+        assert_eq!(Some("20"), cmd.option("limit"));
+        assert_eq!(Some("foo"), cmd.option("user"));
+        assert_eq!(None, cmd.option("verbose"));
+        assert!(cmd.has_option_any(&["verbose"]));
+        assert!(cmd.has_startflag_any(&["--user"]));
+        assert!(cmd.has_endflag_any(&["-verbose"]));
+    }
+
+    #[test]
+    fn negative_numbers_are_arguments() {
+        let cmd = CommandInvocation::parse(&["t;"], "t;add -5 -1.5 - -x")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            vec![String::from("-5"), String::from("-1.5"), String::from("-")],
+            cmd.arguments
+        );
+        assert!(cmd.has_endflag_any(&["-x"]));
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        let cmd = CommandInvocation::parse(&["t;"], "t;opttest -first -- -second --third")
+            .unwrap()
+            .unwrap();
+
+        assert!(cmd.has_option_any(&["first"]));
+        assert!(!cmd.has_option_any(&["second", "third"]));
+        assert_eq!(
+            vec![String::from("-second"), String::from("--third")],
+            cmd.arguments
+        );
+    }
+
+    #[test]
+    fn quoted_arguments() {
+        let cmd = CommandInvocation::parse(&["t;"], "t;say \"hello\nworld\" 'it is' -x")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            vec![String::from("hello\nworld"), String::from("it is")],
+            cmd.arguments
+        );
+        assert!(cmd.has_endflag_any(&["-x"]));
+    }
+
+    #[test]
+    fn invoke_id_generated_once() {
+        let cmd = CommandInvocation::parse(&["t;"], "t;test")
+            .unwrap()
+            .unwrap();
+        assert_eq!(None, cmd.invoke_id);
+
+        let cmd = cmd.with_invoke_id();
+        let invoke_id = cmd.invoke_id.clone().unwrap();
+        assert_eq!(26, invoke_id.len());
+        assert_eq!(Some(invoke_id), cmd.with_invoke_id().invoke_id);
+
+        let other = CommandInvocation::parse(&["t;"], "t;test")
+            .unwrap()
+            .unwrap()
+            .with_invoke_id();
+        assert_ne!(cmd.invoke_id, other.invoke_id);
+    }
+
+    #[test]
+    fn namespaced_command() {
+        let cmd = CommandInvocation::parse(&["t;"], "t;rss:info -v")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(Some(String::from("rss")), cmd.plugin);
+        assert_eq!("info", &cmd.command);
+
+        let cmd = CommandInvocation::parse(&["t;"], "t;:info")
+            .unwrap()
+            .unwrap();
+        assert_eq!(None, cmd.plugin);
+        assert_eq!(":info", &cmd.command);
+    }
+
+    #[test]
+    fn raw_arguments_preserved() {
+        let cmd = CommandInvocation::parse(&["t;"], "t;echo   some   spaced\ntext  ")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!("some   spaced\ntext  ", &cmd.raw_arguments);
+    }
+
+    #[test]
+    fn subcommand_removed_from_raw_arguments() {
+        let mut cmd = CommandInvocation::parse(&["t;"], "t;role -v add  @someone  now")
+            .unwrap()
+            .unwrap();
+        cmd.set_subcommand(vec![String::from("add")]);
+
+        assert_eq!(vec![String::from("add")], cmd.subcommand);
+        assert_eq!("@someone  now", &cmd.raw_arguments);
+        assert!(cmd.has_startflag_any(&["-v"]));
+
+        let mut cmd = CommandInvocation::parse(&["t;"], "t;role LS -v")
+            .unwrap()
+            .unwrap();
+        cmd.set_subcommand(vec![String::from("list")]);
+
+        assert_eq!("-v", &cmd.raw_arguments);
+        assert!(cmd.arguments.is_empty());
+        assert!(cmd.has_startflag_any(&["-v"]));
+    }
+
+    #[test]
+    fn unterminated_quote_is_kept() {
+        let cmd = CommandInvocation::parse(&["t;"], "t;say  \"hello  world -x")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!("say", &cmd.command);
+        assert_eq!("\"hello  world -x", &cmd.raw_arguments);
+        assert_eq!(
+            vec![String::from("\"hello"), String::from("world")],
+            cmd.arguments
+        );
+        assert!(cmd.has_endflag_any(&["-x"]));
+        assert_eq!(
+            Some(TheiaParseError::UnterminatedQuote {
+                quote: '"',
+                position: 7,
+            }),
+            cmd.parse_error
+        );
+
+        assert_eq!(
+            Err(TheiaParseError::UnterminatedQuote {
+                quote: '"',
+                position: 2,
+            }),
+            CommandInvocation::parse(&["t;"], "t;\"say hello")
+        );
+    }
 }
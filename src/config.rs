@@ -1,6 +1,7 @@
 //! Theia bot configuration
 
 use crate::error::TheiaError;
+use crate::plugin::TheiaPluginCapability;
 
 use ::serde::Deserialize;
 use ::serde_json::Value;
@@ -16,6 +17,165 @@ pub struct TheiaRootPluginConfig {
     /// Plugin configuration data
     #[serde(default, rename = "config")]
     pub cfgdata: HashMap<String, Value>,
+
+    /// Plugin priority. Plugins with a higher priority win command name and
+    /// alias collisions with other plugins.
+    #[serde(default)]
+    pub priority: i64,
+
+    /// Overrides for the plugin's commands, keyed by command name
+    #[serde(default)]
+    pub commands: HashMap<String, TheiaCommandOverride>,
+
+    /// Prefix added to the names and aliases of all of the plugin's commands
+    #[serde(default)]
+    pub command_prefix: Option<String>,
+
+    /// Suffix added to the names and aliases of all of the plugin's commands
+    #[serde(default)]
+    pub command_suffix: Option<String>,
+
+    /// Capabilities granted to the plugin. A plugin can only use
+    /// capabilities which it both declares and is granted.
+    #[serde(default)]
+    pub grants: Vec<TheiaPluginCapability>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct TheiaCommandOverride {
+    /// New name for the command
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Aliases to add to the command
+    #[serde(default)]
+    pub add_aliases: Vec<String>,
+
+    /// Aliases to remove from the command
+    #[serde(default)]
+    pub remove_aliases: Vec<String>,
+
+    /// Whether the command is hidden in global command listings
+    #[serde(default)]
+    pub hidden: Option<bool>,
+
+    /// Replacement help text for the command
+    #[serde(default)]
+    pub help: Option<String>,
+
+    /// Whether the command is disabled entirely
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TheiaSuggestionConfig {
+    /// Whether to suggest similar commands when an unknown command is used
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Maximum edit distance between the unknown command and a suggestion
+    #[serde(default = "TheiaSuggestionConfig::default_max_distance")]
+    pub max_distance: usize,
+
+    /// Maximum number of commands to suggest
+    #[serde(default = "TheiaSuggestionConfig::default_max_suggestions")]
+    pub max_suggestions: usize,
+
+    /// Minimum number of seconds between suggestions in a single channel
+    #[serde(default = "TheiaSuggestionConfig::default_cooldown")]
+    pub cooldown: u64,
+}
+
+impl TheiaSuggestionConfig {
+    fn default_max_distance() -> usize {
+        2
+    }
+
+    fn default_max_suggestions() -> usize {
+        3
+    }
+
+    fn default_cooldown() -> u64 {
+        30
+    }
+}
+
+impl Default for TheiaSuggestionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_distance: Self::default_max_distance(),
+            max_suggestions: Self::default_max_suggestions(),
+            cooldown: Self::default_cooldown(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TheiaErrorJournalConfig {
+    /// Path to a file to keep the error journal in, so that it persists
+    /// across restarts. The journal is only kept in memory if unset.
+    ///
+    /// The journal includes the content of messages that caused errors.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Maximum number of errors to keep in the journal
+    #[serde(default = "TheiaErrorJournalConfig::default_max_entries")]
+    pub max_entries: usize,
+
+    /// Maximum age of errors kept in the journal, in seconds
+    #[serde(default = "TheiaErrorJournalConfig::default_max_age")]
+    pub max_age: u64,
+}
+
+impl TheiaErrorJournalConfig {
+    fn default_max_entries() -> usize {
+        1000
+    }
+
+    fn default_max_age() -> u64 {
+        7 * 24 * 60 * 60
+    }
+}
+
+impl Default for TheiaErrorJournalConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_entries: Self::default_max_entries(),
+            max_age: Self::default_max_age(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TheiaLongMessageConfig {
+    /// Maximum number of messages a single plugin run may send, counting
+    /// each part of a split message
+    #[serde(default = "TheiaLongMessageConfig::default_max_messages")]
+    pub max_messages: usize,
+
+    /// Whether content too long to send within `max_messages` is sent as a
+    /// text file attachment instead of being rejected
+    #[serde(default)]
+    pub attachment_fallback: bool,
+}
+
+impl TheiaLongMessageConfig {
+    fn default_max_messages() -> usize {
+        5
+    }
+}
+
+impl Default for TheiaLongMessageConfig {
+    fn default() -> Self {
+        Self {
+            max_messages: Self::default_max_messages(),
+            attachment_fallback: false,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -23,6 +183,11 @@ pub struct TheiaConfig {
     /// List of command prefixes
     pub prefixes: Vec<String>,
 
+    /// Whether prefixes, plugin namespaces, command names and aliases are
+    /// matched NFKC case-insensitively
+    #[serde(default)]
+    pub case_insensitive: bool,
+
     /// List of Discord user IDs that can perform administration commands
     #[serde(default)]
     pub admin_users: Vec<String>,
@@ -30,7 +195,36 @@ pub struct TheiaConfig {
     /// Total shard count.
     pub shard_count: u64,
 
-    /// List of paths to plugins to load
+    /// "Did you mean" suggestions for unknown commands
+    #[serde(default)]
+    pub suggestions: TheiaSuggestionConfig,
+
+    /// Journal of handled errors, which can be looked up by error ID
+    #[serde(default)]
+    pub error_journal: TheiaErrorJournalConfig,
+
+    /// Replies shown to users when an error occurs, keyed by error class,
+    /// overriding the built-in replies
+    ///
+    /// `{error_id}` and `{invoke_id}` in a reply are replaced with the error
+    /// ID and the command invocation ID.
+    #[serde(default)]
+    pub error_replies: HashMap<String, String>,
+
+    /// Path to a file to append the audit log of plugin moderation actions
+    /// to. Actions are always logged through `tracing` as well.
+    #[serde(default)]
+    pub audit_log: Option<PathBuf>,
+
+    /// Handling of plugin messages too long for Discord, which are split
+    /// into several messages
+    #[serde(default)]
+    pub long_messages: TheiaLongMessageConfig,
+
+    /// Plugins to load, keyed by instance ID
+    ///
+    /// The same plugin may be loaded several times under different instance
+    /// IDs, such as with different configuration data.
     #[serde(default, rename = "plugins")]
     pub plugin_cfg: HashMap<String, TheiaRootPluginConfig>,
 }
@@ -52,4 +246,86 @@ impl TheiaConfig {
 
         Ok(config)
     }
+
+    /// The reply template shown to users for an error class.
+    ///
+    /// The built-in classes are `plugin_timeout`, `plugin_crashed`,
+    /// `plugin_unavailable`, `plugin_response`, `permission` and `internal`.
+    /// Unknown classes fall back to the `internal` reply.
+    ///
+    /// Rejected invocations use the `user_permission` class, when the user
+    /// may not use a command (with a `{command}` placeholder), and the
+    /// `arguments` class, when the arguments don't match the command's
+    /// schema (with `{reason}` and `{usage}` placeholders).
+    pub fn error_reply_template<T: AsRef<str>>(&self, class: T) -> &str {
+        let class = match class.as_ref() {
+            "plugin_timeout" | "plugin_crashed" | "plugin_unavailable" | "plugin_response"
+            | "permission" | "user_permission" | "arguments" => class.as_ref(),
+            _ => "internal",
+        };
+
+        if let Some(template) = self.error_replies.get(class) {
+            return template;
+        }
+
+        match class {
+            "plugin_timeout" => "The command took too long to respond. Please try again later.",
+            "plugin_crashed" => "The command failed unexpectedly.",
+            "plugin_unavailable" => "The command is currently unavailable.",
+            "plugin_response" => "The command sent a response that could not be understood.",
+            "permission" => "I don't have permission to do that here.",
+            "user_permission" => "You do not have permission to use `{command}`.",
+            "arguments" => "{reason}\nUsage: `{usage}`",
+            _ => "An internal error occurred.",
+        }
+    }
+
+    /// Renders the reply for a rejected invocation, filling in the given
+    /// placeholders of the class's template.
+    pub fn rejection_reply<T: AsRef<str>>(&self, class: T, values: &[(&str, &str)]) -> String {
+        let reply = values.iter().fold(
+            String::from(self.error_reply_template(class)),
+            |reply, (name, value)| reply.replace(&format!("{{{}}}", name), value),
+        );
+
+        format!("\u{274c} {}", reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_reply_templates() {
+        let config: TheiaConfig = ::toml::from_str(
+            r#"
+                prefixes = ["t;"]
+                shard_count = 1
+
+                [error_replies]
+                plugin_timeout = "Too slow! ({error_id})"
+                internal = "Oops."
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            "Too slow! ({error_id})",
+            config.error_reply_template("plugin_timeout")
+        );
+        assert_eq!(
+            "The command failed unexpectedly.",
+            config.error_reply_template("plugin_crashed")
+        );
+        assert_eq!("Oops.", config.error_reply_template("internal"));
+        assert_eq!("Oops.", config.error_reply_template("unknown"));
+        assert_eq!(
+            "\u{274c} Expected a number\nUsage: `t;roll <sides>`",
+            config.rejection_reply(
+                "arguments",
+                &[("reason", "Expected a number"), ("usage", "t;roll <sides>")]
+            )
+        );
+    }
 }
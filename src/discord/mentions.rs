@@ -0,0 +1,137 @@
+use crate::error::TheiaPluginMessageError;
+use ::serde::{Deserialize, Serialize};
+use ::serenity::builder::{CreateAllowedMentions, ParseValue};
+use ::serenity::model::id::{RoleId, UserId};
+use ::std::str::FromStr;
+
+/// Which mentions in a message's content actually notify anyone.
+///
+/// The default only notifies the author of the message being replied to,
+/// so that content echoing user input can't ping `@everyone` or mass-mention
+/// users and roles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TheiaAllowedMentions {
+    /// Notify every user mentioned in the content.
+    #[serde(default)]
+    pub all_users: bool,
+
+    /// IDs of users to notify, if mentioned in the content.
+    #[serde(default)]
+    pub users: Vec<String>,
+
+    /// Notify every role mentioned in the content.
+    #[serde(default)]
+    pub all_roles: bool,
+
+    /// IDs of roles to notify, if mentioned in the content.
+    #[serde(default)]
+    pub roles: Vec<String>,
+
+    /// Notify `@everyone` and `@here`, if mentioned in the content.
+    #[serde(default)]
+    pub everyone: bool,
+
+    /// Notify the author of the message being replied to.
+    #[serde(default = "TheiaAllowedMentions::default_replied_user")]
+    pub replied_user: bool,
+}
+
+impl TheiaAllowedMentions {
+    fn default_replied_user() -> bool {
+        true
+    }
+
+    /// Check that all user and role IDs are valid.
+    pub fn validate(&self) -> Result<(), TheiaPluginMessageError> {
+        for id in self.users.iter().chain(self.roles.iter()) {
+            u64::from_str(id).map_err(|_| TheiaPluginMessageError::InvalidId(id.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply this policy to a message builder's allowed mentions.
+    ///
+    /// Invalid IDs are skipped; use [`validate`](Self::validate) first to
+    /// report them.
+    pub fn apply<'a>(&self, am: &'a mut CreateAllowedMentions) -> &'a mut CreateAllowedMentions {
+        let ids = |ids: &[String]| -> Vec<u64> {
+            ids.iter().filter_map(|id| u64::from_str(id).ok()).collect()
+        };
+
+        am.empty_parse();
+        if self.all_users {
+            am.parse(ParseValue::Users);
+        } else if !self.users.is_empty() {
+            am.users(ids(&self.users).into_iter().map(UserId::from));
+        }
+
+        if self.all_roles {
+            am.parse(ParseValue::Roles);
+        } else if !self.roles.is_empty() {
+            am.roles(ids(&self.roles).into_iter().map(RoleId::from));
+        }
+
+        if self.everyone {
+            am.parse(ParseValue::Everyone);
+        }
+
+        am.replied_user(self.replied_user)
+    }
+}
+
+impl Default for TheiaAllowedMentions {
+    fn default() -> Self {
+        Self {
+            all_users: false,
+            users: Vec::new(),
+            all_roles: false,
+            roles: Vec::new(),
+            everyone: false,
+            replied_user: Self::default_replied_user(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serde_json::json;
+
+    fn applied(mentions: &TheiaAllowedMentions) -> ::serde_json::Value {
+        let mut am = CreateAllowedMentions::default();
+        mentions.apply(&mut am);
+        ::serde_json::to_value(&am.0).unwrap()
+    }
+
+    #[test]
+    fn default_only_replied_user() {
+        assert_eq!(
+            json!({"parse": [], "replied_user": true}),
+            applied(&Default::default())
+        );
+    }
+
+    #[test]
+    fn explicit_mentions() {
+        let mentions: TheiaAllowedMentions = ::serde_json::from_str(
+            r#"{"users": ["1", "2"], "all_roles": true, "replied_user": false}"#,
+        )
+        .unwrap();
+
+        assert_eq!(Ok(()), mentions.validate());
+        assert_eq!(
+            json!({"parse": ["roles"], "users": ["1", "2"], "replied_user": false}),
+            applied(&mentions)
+        );
+
+        let invalid = TheiaAllowedMentions {
+            roles: vec![String::from("everyone")],
+            ..Default::default()
+        };
+        assert_eq!(
+            Err(TheiaPluginMessageError::InvalidId(String::from("everyone"))),
+            invalid.validate()
+        );
+    }
+}
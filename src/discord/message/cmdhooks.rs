@@ -1,3 +1,6 @@
+use crate::builtin::BUILTIN_COMMANDS;
+use crate::error::TheiaParseError;
+use crate::parser::tokenize::highlight_position;
 use crate::prelude::*;
 
 pub async fn parse_command<'ctx>(
@@ -7,15 +10,78 @@ pub async fn parse_command<'ctx>(
     let data = ctx.data.read().await;
     let theia = data.get::<TheiaContainer>().unwrap();
 
-    if let Some(mut cmd) = CommandInvocation::parse(&theia.prefixes(), &msg.content) {
-        if let Some(plugin) = theia.plugin_with_command(&cmd.command) {
-            if let Some(plugin_cmd) =
-                plugin.config.commands.iter().find(|c| {
-                    &c.name == &cmd.command || c.aliases.iter().any(|a| a == &cmd.command)
-                })
-            {
-                cmd.command = plugin_cmd.name.clone();
+    let prefix = match theia.matched_prefix(&msg.content) {
+        Some(prefix) => prefix,
+        None => return Ok(None),
+    };
+
+    let prefixes: Vec<String> = vec![prefix.clone()];
+    let parsed = match CommandInvocation::parse(&prefixes, &msg.content) {
+        Ok(parsed) => parsed,
+        Err(why) => {
+            // Only point out errors for messages that invoke a real command,
+            // rather than any chat starting with the prefix
+            let cmd_name = msg.content[prefix.len()..]
+                .split_whitespace()
+                .next()
+                .unwrap_or_default();
+
+            if !command_exists(theia, cmd_name) {
+                return Ok(None);
             }
+
+            msg.reply(&ctx.http, parse_error_reply(&msg.content, &why))
+                .await?;
+
+            return Ok(None);
+        }
+    };
+
+    if let Some(mut cmd) = parsed {
+        let resolved = match &cmd.plugin {
+            Some(instance_id) => theia.resolve_plugin_command(instance_id, &cmd.command),
+            None => theia.resolve_command(&cmd.command),
+        };
+
+        if let Some((plugin, plugin_cmd)) = resolved {
+            let value_options: Vec<&String> = ::std::iter::once(plugin_cmd)
+                .chain(plugin_cmd.resolve_subcommands(&cmd.arguments))
+                .flat_map(|c| c.value_options.iter())
+                .collect();
+
+            if !value_options.is_empty() {
+                if let Ok(Some(reparsed)) =
+                    CommandInvocation::parse_with_options(&prefixes, &msg.content, &value_options)
+                {
+                    cmd = reparsed;
+                }
+            }
+
+            let subcommands = plugin_cmd.resolve_subcommands(&cmd.arguments);
+
+            // Arguments that failed to tokenize are only an error for
+            // commands that declare an argument schema
+            let cmdcfg = subcommands.last().copied().unwrap_or(plugin_cmd);
+            if let (Some(why), Some(_)) = (&cmd.parse_error, &cmdcfg.args) {
+                msg.reply(&ctx.http, parse_error_reply(&msg.content, why))
+                    .await?;
+
+                return Ok(None);
+            }
+
+            cmd.set_subcommand(subcommands.iter().map(|s| s.name.clone()).collect());
+
+            cmd.plugin = Some(String::from(plugin.instance_id()));
+            cmd.command = String::from(plugin_cmd.invoke_name());
+        } else if cmd.plugin.is_some() {
+            // Namespaced commands only resolve through the plugin's current
+            // command names and aliases, as unqualified commands do
+            return Ok(None);
+        } else if let Some(builtin) = BUILTIN_COMMANDS
+            .iter()
+            .find(|b| theia.names_match(b, &cmd.command))
+        {
+            cmd.command = String::from(*builtin);
         }
 
         cmd.generate_invoke_id();
@@ -25,3 +91,21 @@ pub async fn parse_command<'ctx>(
         Ok(None)
     }
 }
+
+/// Checks whether a command name, which may be qualified as `plugin:command`,
+/// names a plugin or builtin command.
+fn command_exists(theia: &Theia, cmd_name: &str) -> bool {
+    theia.resolve_qualified_command(cmd_name).is_some()
+        || BUILTIN_COMMANDS
+            .iter()
+            .any(|b| theia.names_match(b, cmd_name))
+}
+
+/// The reply pointing out where a command failed to parse.
+fn parse_error_reply(content: &str, why: &TheiaParseError) -> String {
+    format!(
+        "\u{274c} {0}\n{1}",
+        why,
+        highlight_position(content, why.position()),
+    )
+}
@@ -1,10 +1,23 @@
+use super::mentions::TheiaAllowedMentions;
+use super::split::{split_content, CONTENT_FILENAME, MAX_MESSAGE_LENGTH};
 use crate::prelude::*;
 use ::serde::{Deserialize, Serialize};
+use ::serenity::http::AttachmentType;
 use ::serenity::model::id::{ChannelId, MessageId};
+use ::std::borrow::Cow;
 use ::std::str::FromStr;
 
 pub mod cmdhooks;
 
+/// Maximum number of messages a reply is split into before it is sent as a
+/// text file instead.
+///
+/// This is separate from the `long_messages` configuration, which limits
+/// plugin runs. Replies are Theia's own help, usage and error messages, and
+/// are always sent one way or another, as there is nobody to report a
+/// failure to.
+pub const MAX_REPLY_MESSAGES: usize = 3;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TheiaDiscordMessage {
     pub message_id: String,
@@ -19,6 +32,11 @@ pub struct TheiaDiscordMessage {
 }
 
 impl TheiaDiscordMessage {
+    /// Reply to this message.
+    ///
+    /// Content too long for one message is split into several, or sent as a
+    /// text file if it would take more than [`MAX_REPLY_MESSAGES`]. The first
+    /// message sent is returned.
     pub async fn reply<H: AsRef<serenity::http::Http>>(
         &self,
         http: H,
@@ -27,16 +45,43 @@ impl TheiaDiscordMessage {
         let channel_id = ChannelId::from(u64::from_str(&self.channel_id)?);
         let message_id = MessageId::from(u64::from_str(&self.message_id)?);
 
-        let msg = channel_id
-            .send_message(http, |m| {
-                m.reference_message((channel_id, message_id));
+        let content = content.to_string();
+        let mut chunks = split_content(&content, MAX_MESSAGE_LENGTH);
+        let mut file = None;
+        if chunks.len() > MAX_REPLY_MESSAGES {
+            file = Some(AttachmentType::Bytes {
+                data: Cow::Borrowed(content.as_bytes()),
+                filename: String::from(CONTENT_FILENAME),
+            });
+            chunks = vec![String::new()];
+        }
+
+        let mut first = None;
+        for chunk in chunks.iter() {
+            let msg = channel_id
+                .send_message(http.as_ref(), |m| {
+                    if first.is_none() {
+                        m.reference_message((channel_id, message_id));
+                    }
+
+                    m.allowed_mentions(|am| TheiaAllowedMentions::default().apply(am));
 
-                m.content(content);
-                m
-            })
-            .await?;
+                    if !chunk.is_empty() {
+                        m.content(chunk);
+                    }
+
+                    if let Some(file) = file.take() {
+                        m.add_file(file);
+                    }
+
+                    m
+                })
+                .await?;
+
+            first.get_or_insert(msg);
+        }
 
-        Ok(msg)
+        first.ok_or(TheiaError::UnknownError)
     }
 
     pub async fn parse_as_command<'ctx>(
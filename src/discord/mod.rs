@@ -1 +1,4 @@
+pub mod mentions;
 pub mod message;
+pub mod reaction;
+pub mod split;
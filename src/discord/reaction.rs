@@ -0,0 +1,27 @@
+use ::serde::{Deserialize, Serialize};
+use ::serenity::model::channel::Reaction as SerenityReaction;
+
+/// A reaction added to or removed from a message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TheiaDiscordReaction {
+    pub message_id: String,
+    pub channel_id: String,
+    pub user_id: Option<String>,
+    pub guild_id: Option<String>,
+
+    /// The emoji, either as a Unicode emoji or as `<:name:id>` for custom
+    /// emoji.
+    pub emoji: String,
+}
+
+impl From<SerenityReaction> for TheiaDiscordReaction {
+    fn from(reaction: SerenityReaction) -> TheiaDiscordReaction {
+        TheiaDiscordReaction {
+            message_id: format!("{}", reaction.message_id.0),
+            channel_id: format!("{}", reaction.channel_id.0),
+            user_id: reaction.user_id.map(|e| format!("{}", e.0)),
+            guild_id: reaction.guild_id.map(|e| format!("{}", e.0)),
+            emoji: format!("{}", reaction.emoji),
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Splitting of content too long for a single Discord message.
+
+/// Maximum length of a message's content, in characters.
+pub const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// Filename of text file attachments holding content too long to split.
+pub const CONTENT_FILENAME: &str = "message.txt";
+
+/// Marker opening and closing a fenced code block.
+const FENCE: &str = "```";
+
+/// Split content into chunks of at most `max` characters each.
+///
+/// Content is split between lines where possible, and lines too long for a
+/// single chunk are split at whitespace. Fenced code blocks split across
+/// chunks are closed at the end of each chunk, and re-opened with the same
+/// info string at the start of the next, unless the fence and info string
+/// are too long to leave enough room for the code.
+pub fn split_content(content: &str, max: usize) -> Vec<String> {
+    if content.chars().count() <= max {
+        return vec![String::from(content)];
+    }
+
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+
+    // Whether a code block is open, the fence and info string to re-open it
+    // with, and the length of the chunk after re-opening it
+    let mut in_block = false;
+    let mut fence: Option<String> = None;
+    let mut base_len = 0;
+
+    for line in content.split_inclusive('\n') {
+        let closes = in_block && line.trim_start().starts_with(FENCE);
+        let opens = !in_block && opening_fence(line).is_some();
+        let next_fence = if closes {
+            None
+        } else if opens {
+            // Re-opening the block must leave at least a quarter of each
+            // chunk for the code
+            opening_fence(line).filter(|opening| {
+                4 * (opening.chars().count() + closing_fence(opening).len() + 2) <= 3 * max
+            })
+        } else {
+            fence.clone()
+        };
+
+        let mut rest = line;
+        while !rest.is_empty() {
+            // Leave room to close the code block, if one is open after this
+            // line
+            let reserved = next_fence
+                .as_deref()
+                .map_or(0, |opening| closing_fence(opening).len() + 1);
+            let used = chunk.chars().count();
+            let available = max.saturating_sub(used + reserved);
+
+            if rest.chars().count() <= available {
+                chunk.push_str(rest);
+                rest = "";
+            } else if used > base_len {
+                flush(&mut chunks, &mut chunk, &fence);
+                base_len = chunk.chars().count();
+            } else {
+                let (head, tail) = split_line(rest, available.max(1));
+                chunk.push_str(head);
+                rest = tail;
+                flush(&mut chunks, &mut chunk, &fence);
+                base_len = chunk.chars().count();
+            }
+        }
+
+        in_block = (in_block || opens) && !closes;
+        fence = next_fence;
+    }
+
+    fence = None;
+    flush(&mut chunks, &mut chunk, &fence);
+    chunks
+}
+
+/// Finish a chunk, closing and re-opening the current code block if any.
+///
+/// A code block opened on the last line of the chunk is moved to the next
+/// chunk instead, rather than leaving an empty code block behind.
+fn flush(chunks: &mut Vec<String>, chunk: &mut String, fence: &Option<String>) {
+    let mut finished = ::std::mem::take(chunk);
+    if let Some(opening) = fence {
+        let body = finished.trim_end_matches('\n');
+        let (before, last) = body.rsplit_once('\n').unwrap_or(("", body));
+        if opening_fence(last).as_ref() == Some(opening) {
+            finished = String::from(before);
+        } else {
+            if !finished.ends_with('\n') {
+                finished.push('\n');
+            }
+
+            finished.push_str(closing_fence(opening));
+        }
+
+        chunk.push_str(opening);
+        chunk.push('\n');
+    }
+
+    let finished = finished.trim_end();
+    if !finished.is_empty() {
+        chunks.push(String::from(finished));
+    }
+}
+
+/// The fence and info string of a line opening a code block, if it opens
+/// one.
+///
+/// Lines with another fence after the opening one hold inline code instead.
+fn opening_fence(line: &str) -> Option<String> {
+    let line = line.trim();
+    let info = line.trim_start_matches('`');
+    let fence = &line[..line.len() - info.len()];
+    if fence.len() < FENCE.len() || info.contains(FENCE) {
+        return None;
+    }
+
+    let info = info.split_whitespace().next().unwrap_or_default();
+    Some(format!("{}{}", fence, info))
+}
+
+/// The fence closing a code block, given the fence and info string opening
+/// it.
+fn closing_fence(opening: &str) -> &str {
+    let info = opening.trim_start_matches('`');
+    &opening[..opening.len() - info.len()]
+}
+
+/// Split a line after at most `max` characters, at the last whitespace if
+/// there is any.
+fn split_line(line: &str, max: usize) -> (&str, &str) {
+    let end = line
+        .char_indices()
+        .nth(max)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len());
+
+    let at = line[..end]
+        .rfind(char::is_whitespace)
+        .filter(|&i| i > 0)
+        .map(|i| i + line[i..].chars().next().map_or(1, char::len_utf8))
+        .unwrap_or(end);
+
+    line.split_at(at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_content_unchanged() {
+        assert_eq!(vec!["hello\nworld"], split_content("hello\nworld", 20));
+        assert_eq!(vec![""], split_content("", 20));
+    }
+
+    #[test]
+    fn splits_on_lines() {
+        let content = "first line\nsecond line\nthird line";
+        assert_eq!(
+            vec!["first line", "second line", "third line"],
+            split_content(content, 15)
+        );
+        assert_eq!(
+            vec!["first line\nsecond line", "third line"],
+            split_content(content, 25)
+        );
+    }
+
+    #[test]
+    fn splits_long_lines() {
+        let chunks = split_content("aaaa bbbb cccc dddd", 10);
+        assert_eq!(vec!["aaaa bbbb", "cccc dddd"], chunks);
+
+        let chunks = split_content(&"x".repeat(25), 10);
+        assert_eq!(vec!["x".repeat(10), "x".repeat(10), "x".repeat(5)], chunks);
+
+        let chunks = split_content(&"é".repeat(15), 10);
+        assert_eq!(vec!["é".repeat(10), "é".repeat(5)], chunks);
+    }
+
+    #[test]
+    fn reopens_code_blocks() {
+        let content = "before\n```rust\nlet a = 1;\nlet b = 2;\nlet c = 3;\n```\nafter";
+        let chunks = split_content(content, 30);
+        assert_eq!(
+            vec![
+                "before\n```rust\nlet a = 1;\n```",
+                "```rust\nlet b = 2;\n```",
+                "```rust\nlet c = 3;\n```\nafter",
+            ],
+            chunks
+        );
+
+        // Code blocks opening at the end of a chunk
+        let edge = format!("{}\n```\n{}\n```", "a".repeat(1995), "b".repeat(10));
+        let chunks = split_content(&edge, MAX_MESSAGE_LENGTH);
+        assert_eq!(
+            vec!["a".repeat(1995), format!("```\n{}\n```", "b".repeat(10))],
+            chunks
+        );
+
+        // Inline code isn't a code block
+        let inline = format!("```ls``` lists files\n{}", "word ".repeat(30));
+        let chunks = split_content(&inline, 40);
+        assert_eq!(1, chunks.iter().filter(|c| c.contains("```ls```")).count());
+        assert!(
+            chunks.iter().all(|c| c.chars().count() <= 40),
+            "{:?}",
+            chunks
+        );
+
+        // Code blocks whose opening line doesn't fit aren't re-opened
+        let long = format!("```{}\n{}\n```", "x".repeat(40), "code ".repeat(20));
+        for max in 20..50 {
+            let chunks = split_content(&long, max);
+            assert!(
+                chunks.iter().all(|c| c.chars().count() <= max),
+                "{:?}",
+                chunks
+            );
+            assert_eq!(1, chunks.iter().filter(|c| c.starts_with("```x")).count());
+        }
+
+        let inputs = [content.repeat(20), edge, inline];
+        for input in inputs.iter() {
+            for max in 20..80 {
+                for chunk in split_content(input, max) {
+                    assert!(chunk.chars().count() <= max, "{:?}", chunk);
+                    assert_eq!(0, chunk.matches(FENCE).count() % 2, "{:?}", chunk);
+
+                    let lines: Vec<&str> = chunk.lines().collect();
+                    let empty_block = lines
+                        .windows(2)
+                        .any(|w| w[0].starts_with(FENCE) && w[1] == FENCE);
+                    assert!(!empty_block, "{:?}", chunk);
+                }
+            }
+        }
+    }
+}
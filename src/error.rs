@@ -22,6 +22,9 @@ pub enum TheiaError {
     /// The plugin `{0}` failed to run: {1}
     PluginRun(String, TheiaPluginRunError),
 
+    /// The plugin `{0}` sent an invalid message: {1}
+    PluginMessage(String, TheiaPluginMessageError),
+
     /// Serenity error: {0}
     SerenityError(::serenity::Error),
 
@@ -44,6 +47,48 @@ pub enum TheiaError {
     UnknownError,
 }
 
+impl TheiaError {
+    /// The class of this error, used to pick the reply shown to users.
+    ///
+    /// See [`TheiaConfig::error_reply_template`](crate::config::TheiaConfig::error_reply_template)
+    /// for the list of classes.
+    pub fn class(&self) -> &'static str {
+        match self {
+            Self::PluginRun(_, TheiaPluginRunError::Timeout(_)) => "plugin_timeout",
+            Self::PluginRun(_, _) => "plugin_crashed",
+            Self::PluginLoad(_, _) => "plugin_unavailable",
+            Self::PluginMessage(_, _)
+            | Self::SerdeJsonError(_)
+            | Self::Utf8Error(_)
+            | Self::FromUtf8Error(_) => "plugin_response",
+
+            Self::SerenityError(::serenity::Error::Model(
+                ::serenity::model::ModelError::InvalidPermissions(_),
+            )) => "permission",
+            Self::SerenityError(::serenity::Error::Http(e))
+                if e.status_code().map(|s| s.as_u16()) == Some(403) =>
+            {
+                "permission"
+            }
+
+            _ => "internal",
+        }
+    }
+
+    /// The JSON error code of a failed Discord API request, if this error
+    /// is one.
+    pub fn discord_error_code(&self) -> Option<isize> {
+        match self {
+            Self::SerenityError(::serenity::Error::Http(e)) => match &**e {
+                ::serenity::http::HttpError::UnsuccessfulRequest(resp) => Some(resp.error.code),
+                _ => None,
+            },
+
+            _ => None,
+        }
+    }
+}
+
 impl Error for TheiaError {}
 
 impl From<::serenity::Error> for TheiaError {
@@ -91,6 +136,9 @@ pub enum TheiaPluginLoadError {
 
     /// Configuration failed to parse: {0}
     ConfigParseError(::toml::de::Error),
+
+    /// Variadic argument `{argument}` of command `{command}` is not the last argument
+    NonFinalVariadic { command: String, argument: String },
 }
 
 impl Error for TheiaPluginLoadError {}
@@ -104,10 +152,158 @@ pub enum TheiaPluginRunError {
 
     /// Plugin was terminated by signal
     Terminated,
+
+    /// Plugin did not finish within {0} seconds
+    Timeout(u64),
 }
 
 impl Error for TheiaPluginRunError {}
 
+/// Errors in messages sent by plugins.
+#[derive(Display, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum TheiaPluginMessageError {
+    /// Message has no content, embeds or attachments
+    EmptyMessage,
+
+    /// Content is too long ({length} characters, at most {limit} are allowed)
+    ContentTooLong { length: usize, limit: usize },
+
+    /// `{0}` is not a valid Discord ID
+    InvalidId(String),
+
+    /// Too many embeds ({count}, at most {limit} are allowed)
+    TooManyEmbeds { count: usize, limit: usize },
+
+    /// Too many fields in `embeds[{embed}]` ({count}, at most {limit} are allowed)
+    TooManyEmbedFields {
+        embed: usize,
+        count: usize,
+        limit: usize,
+    },
+
+    /// `{field}` is too long ({length} characters, at most {limit} are allowed)
+    EmbedFieldTooLong {
+        field: String,
+        length: usize,
+        limit: usize,
+    },
+
+    /// `{0}` must not be empty
+    EmptyEmbedField(String),
+
+    /// `{field}` must be {expected}
+    InvalidEmbedField {
+        field: String,
+        expected: &'static str,
+    },
+
+    /// Embeds are too long in total ({length} characters, at most {limit} are allowed)
+    EmbedsTooLong { length: usize, limit: usize },
+
+    /// No message was sent with the handle `{0}` in this invocation
+    UnknownHandle(String),
+
+    /// Message must be referenced by a handle, or by channel ID and message ID
+    NoMessageReference,
+
+    /// Message `{0}` was not sent by this plugin
+    NotOwnMessage(String),
+
+    /// `{0}` is not a valid emoji
+    InvalidEmoji(String),
+
+    /// User `{0}` does not accept direct messages from the bot
+    DirectMessagesDisabled(String),
+
+    /// Plugin does not declare the `{0}` capability in its manifest
+    CapabilityNotDeclared(&'static str),
+
+    /// Plugin has not been granted the `{0}` capability
+    CapabilityNotGranted(&'static str),
+
+    /// No guild was given, and the plugin was not invoked in a guild
+    NoGuild,
+
+    /// Timeouts can last at most {limit} seconds, not {duration}
+    TimeoutTooLong { duration: u64, limit: u64 },
+
+    /// Bans can delete at most {limit} days of messages, not {days}
+    BanDeleteDaysTooMany { days: u8, limit: u8 },
+
+    /// Bulk deletes must cover between 1 and {limit} messages, not {count}
+    BulkDeleteCount { count: usize, limit: usize },
+
+    /// Too many attachments ({count}, at most {limit} are allowed)
+    TooManyAttachments { count: usize, limit: usize },
+
+    /// Too many messages sent in a single run (at most {limit} are allowed)
+    TooManyMessages { limit: usize },
+
+    /// Attachments are too large ({size} bytes, at most {limit} are allowed)
+    AttachmentsTooLarge { size: usize, limit: usize },
+
+    /// `{0}` is not a valid attachment filename
+    InvalidFilename(String),
+
+    /// Attachment `{0}` must have exactly one of `data` or `path`
+    AttachmentSource(String),
+
+    /// Attachment `{0}` does not contain valid base64 data
+    InvalidAttachmentData(String),
+
+    /// Attachment path `{0}` leads outside of the plugin directory
+    AttachmentPathEscape(String),
+
+    /// Attachment `{path}` could not be read: {reason}
+    AttachmentUnreadable { path: String, reason: String },
+}
+
+impl Error for TheiaPluginMessageError {}
+
+/// Command parse errors.
+#[derive(Display, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TheiaParseError {
+    /// Unterminated `{quote}` quote starting at position {position}
+    UnterminatedQuote { quote: char, position: usize },
+
+    /// Unterminated `{fence}` code starting at position {position}
+    UnterminatedCode { fence: String, position: usize },
+}
+
+impl TheiaParseError {
+    /// Character position in the input at which the error occurred.
+    pub fn position(&self) -> usize {
+        match self {
+            Self::UnterminatedQuote { position, .. } => *position,
+            Self::UnterminatedCode { position, .. } => *position,
+        }
+    }
+}
+
+impl Error for TheiaParseError {}
+
+/// Command argument errors.
+#[derive(Display, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum TheiaArgumentError {
+    /// Missing required argument `{0}`
+    Missing(String),
+
+    /// Too many arguments (expected at most {0})
+    TooMany(usize),
+
+    /// Invalid value `{value}` for argument `{name}` (expected {expected})
+    Invalid {
+        name: String,
+        value: String,
+        expected: String,
+    },
+}
+
+impl Error for TheiaArgumentError {}
+
 /// CLI errors.
 #[derive(Display, Debug, PartialEq)]
 #[non_exhaustive]
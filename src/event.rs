@@ -1,7 +1,12 @@
+use crate::journal::TheiaErrorRecord;
+use crate::plugin::comms::TheiaPluginOutgoingMessage;
+use crate::plugin::TheiaPluginEventKind;
 use crate::prelude::*;
 use ::serenity::client::EventHandler;
+use ::serenity::model::channel::Reaction;
 use ::serenity::model::gateway::{Activity, Ready};
 use ::serenity::model::user::OnlineStatus;
+use ::tracing::Instrument;
 
 /// The Serenity event handler
 pub struct TheiaEventHandler;
@@ -13,20 +18,110 @@ impl TheiaEventHandler {
         msg: &TheiaDiscordMessage,
         why: TheiaError,
     ) {
-        let errcode = self.handle_err(why).await;
+        let class = why.class();
+        let details = format!("{}", why);
+        let errcode = self.handle_err(ctx, Some(msg), why).await;
+        let invoke_id = msg
+            .command_invocation
+            .as_ref()
+            .and_then(|cmd| cmd.invoke_id.as_deref())
+            .unwrap_or_default();
+
+        let data = ctx.data.read().await;
+        let theia = data.get::<TheiaContainer>();
+        let template = match theia {
+            Some(theia) => theia.config.error_reply_template(class),
+            None => "An internal error occurred.",
+        };
+
+        let mut reply = format!(
+            "\u{274c} {0}",
+            template
+                .replace("{error_id}", &errcode)
+                .replace("{invoke_id}", invoke_id)
+        );
+
+        let mut ids = Vec::new();
+        if !template.contains("{error_id}") {
+            ids.push(format!("error `{}`", errcode));
+        }
+
+        if !invoke_id.is_empty() && !template.contains("{invoke_id}") {
+            ids.push(format!("invocation `{}`", invoke_id));
+        }
+
+        if !ids.is_empty() {
+            reply.push_str(&format!(" ({})", ids.join(", ")));
+        }
+
+        // Internal details are only shown to admins
+        if theia.is_some_and(|theia| theia.is_admin(&msg.author_id)) {
+            reply.push_str(&format!("\n> {}", details));
+        }
 
         // Ignore any errors while sending the error message
-        let _ = msg
-            .reply(
-                &ctx.http,
-                format!("\u{274c} An error occurred: `{0}`", errcode),
-            )
-            .await;
+        let _ = msg.reply(&ctx.http, reply).await;
     }
 
-    pub async fn handle_err(&self, why: TheiaError) -> String {
-        error!("Unhandled TheiaError: {:?}", why);
-        return String::from("00000000000000000000000000000000");
+    /// Record an error in the error journal, returning its error ID.
+    pub async fn handle_err(
+        &self,
+        ctx: &SerenityContext,
+        msg: Option<&TheiaDiscordMessage>,
+        why: TheiaError,
+    ) -> String {
+        let record = TheiaErrorRecord::new(&why, msg);
+        error!("Unhandled TheiaError {}: {:?}", record.error_id, why);
+
+        let data = ctx.data.read().await;
+        match data.get::<TheiaContainer>() {
+            Some(theia) => theia.journal.record(record).await,
+            None => record.error_id,
+        }
+    }
+}
+
+impl TheiaEventHandler {
+    /// Send a reaction event to the plugin that sent the reacted-to message,
+    /// if it subscribed to the event.
+    async fn route_reaction(&self, ctx: &SerenityContext, reaction: Reaction, added: bool) {
+        if reaction.user_id == Some(ctx.cache.current_user_id().await) {
+            return;
+        }
+
+        let data = ctx.data.read().await;
+        let theia = data.get::<TheiaContainer>().unwrap();
+
+        let (kind, event) = if added {
+            (
+                TheiaPluginEventKind::ReactionAdd,
+                TheiaPluginOutgoingMessage::ReactionAdd {
+                    reaction: reaction.clone().into(),
+                },
+            )
+        } else {
+            (
+                TheiaPluginEventKind::ReactionRemove,
+                TheiaPluginOutgoingMessage::ReactionRemove {
+                    reaction: reaction.clone().into(),
+                },
+            )
+        };
+
+        let plugin = match theia.message_owner(reaction.message_id.to_string()) {
+            Some(plugin) if plugin.config.subscribes_to(kind) => plugin,
+            _ => return,
+        };
+
+        let msgs = vec![
+            TheiaPluginOutgoingMessage::bot_info(ctx).await,
+            TheiaPluginOutgoingMessage::plugin_config(plugin).await,
+            event,
+        ];
+
+        if let Err(why) = plugin.invoke(ctx, &msgs).await {
+            self.handle_err(ctx, None, why).await;
+        }
     }
 }
 
@@ -46,6 +141,14 @@ impl EventHandler for TheiaEventHandler {
             .await;
     }
 
+    async fn reaction_add(&self, ctx: SerenityContext, reaction: Reaction) {
+        self.route_reaction(&ctx, reaction, true).await;
+    }
+
+    async fn reaction_remove(&self, ctx: SerenityContext, reaction: Reaction) {
+        self.route_reaction(&ctx, reaction, false).await;
+    }
+
     async fn message(&self, ctx: SerenityContext, orig_msg: SerenityDiscordMessage) {
         let msg = TheiaDiscordMessage::from(orig_msg.clone());
         let cmd_msg = match msg.parse_as_command(&ctx).await {
@@ -61,7 +164,16 @@ impl EventHandler for TheiaEventHandler {
             Ok(Some(cmd_msg)) => cmd_msg,
         };
 
-        if let Err(why) = invoke_command(&ctx, &cmd_msg).await {
+        let span = ::tracing::info_span!(
+            "invoke_command",
+            invoke_id = cmd_msg
+                .command_invocation
+                .as_ref()
+                .and_then(|cmd| cmd.invoke_id.as_deref())
+                .unwrap_or_default(),
+        );
+
+        if let Err(why) = invoke_command(&ctx, &cmd_msg).instrument(span).await {
             self.handle_err_reply(&ctx, &cmd_msg, why).await;
         }
     }
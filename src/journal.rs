@@ -0,0 +1,298 @@
+//! The error journal.
+//!
+//! Every error handled by the bot is recorded here under a unique error ID,
+//! which is shown to the user so that the full details can be looked up by
+//! an admin later.
+//!
+//! Records include the content of the message being handled, so a journal
+//! file should be kept as private as the messages themselves.
+
+use crate::config::TheiaErrorJournalConfig;
+use crate::prelude::*;
+
+use ::serde::{Deserialize, Serialize};
+use ::std::collections::VecDeque;
+use ::std::fs::{self, OpenOptions};
+use ::std::io::Write;
+use ::std::path::{Path, PathBuf};
+use ::std::sync::Mutex;
+use ::std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single error recorded in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TheiaErrorRecord {
+    /// Unique error ID
+    pub error_id: String,
+
+    /// Time the error occurred, in seconds since the Unix epoch
+    pub timestamp: u64,
+
+    /// The error, as displayed to admins
+    pub error: String,
+
+    /// The error's debug representation
+    pub error_debug: String,
+
+    /// Instance ID of the plugin involved in the error, if any
+    pub plugin: Option<String>,
+
+    /// The message being handled when the error occurred, if any, including
+    /// its content and command invocation
+    pub message: Option<TheiaDiscordMessage>,
+}
+
+impl TheiaErrorRecord {
+    /// Create a record for an error, with a new error ID.
+    pub fn new(why: &TheiaError, message: Option<&TheiaDiscordMessage>) -> Self {
+        let plugin = message
+            .and_then(|m| m.command_invocation.as_ref())
+            .and_then(|cmd| cmd.plugin.clone())
+            .or_else(|| match why {
+                TheiaError::PluginRun(plugin, _) => Some(plugin.clone()),
+                _ => None,
+            });
+
+        Self {
+            error_id: ::ulid::Ulid::new().to_string(),
+            timestamp: now(),
+            error: format!("{}", why),
+            error_debug: format!("{:?}", why),
+            plugin,
+            message: message.cloned(),
+        }
+    }
+}
+
+/// Journal of recent errors, with retention limits.
+///
+/// Records are kept in memory, and if a path is configured, also appended to
+/// a JSON-lines file so that they survive restarts. The file is rewritten
+/// with only the retained records when the journal is opened, and whenever
+/// it grows to twice the maximum number of entries.
+#[derive(Debug)]
+pub struct TheiaErrorJournal {
+    path: Option<PathBuf>,
+    max_entries: usize,
+    max_age: u64,
+    records: Mutex<VecDeque<TheiaErrorRecord>>,
+
+    /// Number of records in the file, held while writing to it
+    file_entries: ::tokio::sync::Mutex<usize>,
+}
+
+impl TheiaErrorJournal {
+    /// Open an error journal, loading any existing records from its file.
+    pub fn open(config: &TheiaErrorJournalConfig) -> Self {
+        let mut journal = Self {
+            path: config.path.clone(),
+            max_entries: config.max_entries,
+            max_age: config.max_age,
+            records: Default::default(),
+            file_entries: Default::default(),
+        };
+
+        if let Some(path) = &journal.path {
+            if let Ok(contents) = fs::read_to_string(path) {
+                let mut records = journal.records.lock().unwrap();
+                for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+                    match ::serde_json::from_str::<TheiaErrorRecord>(line) {
+                        Ok(record) => records.push_back(record),
+                        Err(e) => warn!("Skipping invalid error journal entry: {}", e),
+                    }
+                }
+            }
+        }
+
+        journal.prune(now());
+        if let Some(path) = &journal.path {
+            let contents = journal.contents();
+            *journal.file_entries.get_mut() = journal.records.lock().unwrap().len();
+            if let Err(e) = write_atomic(path, &contents) {
+                warn!("Failed to write error journal: {}", e);
+            }
+        }
+
+        journal
+    }
+
+    /// Record an error, returning its error ID.
+    pub async fn record(&self, record: TheiaErrorRecord) -> String {
+        let error_id = record.error_id.clone();
+        let timestamp = record.timestamp;
+        let line = ::serde_json::to_string(&record);
+        self.records.lock().unwrap().push_back(record);
+        self.prune(timestamp);
+
+        if let Err(e) = self.save(line).await {
+            warn!("Failed to write error journal: {}", e);
+        }
+
+        error_id
+    }
+
+    /// Look up the record for an error ID.
+    pub fn get<T: AsRef<str>>(&self, error_id: T) -> Option<TheiaErrorRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|r| r.error_id.eq_ignore_ascii_case(error_id.as_ref()))
+            .cloned()
+    }
+
+    /// Drop records beyond the retention limits, as of the given time.
+    fn prune(&self, now: u64) {
+        let mut records = self.records.lock().unwrap();
+        records.retain(|r| now.saturating_sub(r.timestamp) <= self.max_age);
+        while records.len() > self.max_entries {
+            records.pop_front();
+        }
+    }
+
+    /// The retained records, as JSON lines.
+    fn contents(&self) -> String {
+        let mut contents = String::new();
+        for record in self.records.lock().unwrap().iter() {
+            if let Ok(line) = ::serde_json::to_string(record) {
+                contents.push_str(&line);
+                contents.push('\n');
+            }
+        }
+
+        contents
+    }
+
+    /// Append a newly recorded error to the file, or rewrite the file if it
+    /// has grown too large.
+    async fn save(&self, line: Result<String, ::serde_json::Error>) -> Result<(), TheiaError> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return Ok(()),
+        };
+
+        let mut file_entries = self.file_entries.lock().await;
+        if *file_entries >= self.max_entries.saturating_mul(2) {
+            let contents = self.contents();
+            *file_entries = self.records.lock().unwrap().len();
+            ::tokio::task::spawn_blocking(move || write_atomic(&path, &contents))
+                .await
+                .map_err(|_| TheiaError::UnknownError)??;
+        } else {
+            let line = format!("{}\n", line?);
+            ::tokio::task::spawn_blocking(move || {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)?
+                    .write_all(line.as_bytes())
+            })
+            .await
+            .map_err(|_| TheiaError::UnknownError)??;
+            *file_entries += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for TheiaErrorJournal {
+    fn default() -> Self {
+        Self::open(&Default::default())
+    }
+}
+
+/// Replace the contents of a file by writing to a temporary file and renaming
+/// it over the original, so that a crash never leaves it truncated.
+fn write_atomic(path: &Path, contents: &str) -> Result<(), ::std::io::Error> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(max_entries: usize, max_age: u64) -> TheiaErrorJournal {
+        TheiaErrorJournal::open(&TheiaErrorJournalConfig {
+            path: None,
+            max_entries,
+            max_age,
+        })
+    }
+
+    #[tokio::test]
+    async fn record_and_lookup() {
+        let journal = journal(10, 60);
+        let record = TheiaErrorRecord::new(&TheiaError::UnknownError, None);
+        let error_id = journal.record(record.clone()).await;
+
+        assert_eq!(26, error_id.len());
+        let found = journal.get(error_id.to_lowercase()).unwrap();
+        assert_eq!(record.error_id, found.error_id);
+        assert_eq!("An unknown error occurred", found.error);
+        assert!(journal.get("nonexistent").is_none());
+    }
+
+    #[tokio::test]
+    async fn retention_limits() {
+        let journal = journal(2, 60);
+        let mut records: Vec<TheiaErrorRecord> = (0..3)
+            .map(|_| TheiaErrorRecord::new(&TheiaError::UnknownError, None))
+            .collect();
+        records[0].timestamp -= 120;
+
+        for record in records.iter() {
+            journal.record(record.clone()).await;
+        }
+
+        assert!(journal.get(&records[0].error_id).is_none());
+        assert!(journal.get(&records[1].error_id).is_some());
+        assert!(journal.get(&records[2].error_id).is_some());
+
+        let journal = self::journal(1, 60);
+        for record in records.iter().skip(1) {
+            journal.record(record.clone()).await;
+        }
+
+        assert!(journal.get(&records[1].error_id).is_none());
+        assert!(journal.get(&records[2].error_id).is_some());
+    }
+
+    #[tokio::test]
+    async fn persisted_to_file() {
+        let path = ::std::env::temp_dir().join(format!("theia-journal-{}", ::ulid::Ulid::new()));
+        let config = TheiaErrorJournalConfig {
+            path: Some(path.clone()),
+            max_entries: 2,
+            max_age: 60,
+        };
+
+        let journal = TheiaErrorJournal::open(&config);
+        let mut error_ids = Vec::new();
+        for _ in 0..6 {
+            let record = TheiaErrorRecord::new(&TheiaError::UnknownError, None);
+            error_ids.push(journal.record(record).await);
+        }
+
+        // Rewritten on the fifth record, then appended to
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(3, lines);
+
+        let reopened = TheiaErrorJournal::open(&config);
+        assert!(reopened.get(&error_ids[3]).is_none());
+        assert!(reopened.get(&error_ids[4]).is_some());
+        assert!(reopened.get(&error_ids[5]).is_some());
+        assert_eq!(2, fs::read_to_string(&path).unwrap().lines().count());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,10 +1,13 @@
 //! The core of Theia.
 
+pub mod audit;
+pub mod builtin;
 pub mod command;
 pub mod config;
 pub mod discord;
 pub mod error;
 pub mod event;
+pub mod journal;
 pub mod parser;
 pub mod plugin;
 pub mod typemap;
@@ -13,16 +16,38 @@ pub mod util;
 pub mod prelude;
 use self::prelude::*;
 
+use self::audit::TheiaAuditLog;
+use self::config::TheiaRootPluginConfig;
 use self::event::TheiaEventHandler;
+use self::journal::TheiaErrorJournal;
+use self::plugin::{TheiaCommandRegistry, TheiaMessageOwners, TheiaPluginCommandConfig};
+use self::util::normalize::{normalize_name, normalized_prefix_len};
 use ::serenity::client::Client as SerenityClient;
+use ::std::collections::HashMap;
 use ::std::path::{Path, PathBuf};
 use ::std::str::FromStr;
-use ::std::sync::Arc;
+use ::std::sync::{Arc, Mutex};
+use ::std::time::{Duration, Instant};
 
 pub struct Theia {
     pub config_path: PathBuf,
     pub config: TheiaConfig,
     plugins: Vec<TheiaPlugin>,
+
+    /// Mapping of command names and aliases to plugin commands
+    registry: TheiaCommandRegistry,
+
+    /// Time of the last command suggestion sent in each channel
+    suggestion_times: Mutex<HashMap<String, Instant>>,
+
+    /// Journal of handled errors
+    pub journal: TheiaErrorJournal,
+
+    /// Log of actions taken by plugins
+    pub audit: TheiaAuditLog,
+
+    /// Plugin instances that sent the bot's recent messages
+    message_owners: Mutex<TheiaMessageOwners>,
 }
 
 impl Theia {
@@ -33,6 +58,11 @@ impl Theia {
             config_path,
             config: Default::default(),
             plugins: Vec::new(),
+            registry: Default::default(),
+            suggestion_times: Default::default(),
+            journal: Default::default(),
+            audit: Default::default(),
+            message_owners: Default::default(),
         })
     }
 
@@ -41,21 +71,53 @@ impl Theia {
 
         // Load config
         self.config = TheiaConfig::new(&self.config_path)?;
+        self.journal = TheiaErrorJournal::open(&self.config.error_journal);
+        self.audit = TheiaAuditLog::new(self.config.audit_log.clone());
+
+        // Load plugins, in priority order
+        let mut plugin_cfgs: Vec<(&String, &TheiaRootPluginConfig)> =
+            self.config.plugin_cfg.iter().collect();
+        plugin_cfgs.sort_by(|(a_name, a), (b_name, b)| {
+            b.priority.cmp(&a.priority).then(a_name.cmp(b_name))
+        });
 
-        // Load plugins
         self.plugins = Vec::new();
-        for (_plugin_name, plugin_cfg) in self.config.plugin_cfg.iter() {
+        for (instance_id, plugin_cfg) in plugin_cfgs.into_iter() {
             let mut plugin = TheiaPlugin::new(&plugin_cfg.path)?;
+            plugin.instance_id = instance_id.clone();
+            plugin.grants = plugin_cfg.grants.clone();
             info!(
-                "Loaded plugin {0:?} (from {path:?})",
+                "Loaded plugin {0:?} as {1:?} (from {path:?})",
                 plugin.config.name,
+                plugin.instance_id,
                 path = &plugin.path,
             );
 
             plugin.configure(&plugin_cfg.cfgdata).await?;
+            plugin
+                .config
+                .set_case_insensitive(self.config.case_insensitive);
+            plugin.config.apply_command_overrides(&plugin_cfg.commands);
+            plugin.config.apply_command_affixes(
+                plugin_cfg.command_prefix.as_deref(),
+                plugin_cfg.command_suffix.as_deref(),
+            );
             self.plugins.push(plugin);
         }
 
+        // Build the command registry
+        self.registry = TheiaCommandRegistry::build(&self.plugins, self.config.case_insensitive);
+        for collision in self.registry.collisions().iter() {
+            warn!(
+                "Command name {0:?} of command {1:?} in plugin {2:?} collides with command {3:?} in plugin {4:?}, which takes precedence",
+                collision.name,
+                collision.shadowed.1,
+                collision.shadowed.0,
+                collision.winner.1,
+                collision.winner.0,
+            );
+        }
+
         Ok(())
     }
 
@@ -102,37 +164,242 @@ impl<'a> Theia {
         self.config.prefixes.iter().map(String::clone).collect()
     }
 
-    pub fn plugin<T: AsRef<str>>(&'a self, name: T) -> Option<&'a TheiaPlugin> {
-        self.plugins.iter().find(|p| p.config.name == name.as_ref())
+    /// Find the prefix which `input` starts with, as written in `input`.
+    ///
+    /// With case-insensitive matching enabled, this may differ from the
+    /// configured prefix, such as `T;` for a configured `t;`.
+    pub fn matched_prefix<T: AsRef<str>>(&'a self, input: T) -> Option<String> {
+        let input = input.as_ref();
+        self.config.prefixes.iter().rev().find_map(|prefix| {
+            if self.config.case_insensitive {
+                normalized_prefix_len(input, prefix).map(|len| String::from(&input[..len]))
+            } else if input.starts_with(prefix.as_str()) {
+                Some(prefix.clone())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Checks whether two command names match, taking case-insensitive
+    /// matching into account if it is enabled.
+    pub fn names_match<A: AsRef<str>, B: AsRef<str>>(&'a self, a: A, b: B) -> bool {
+        if self.config.case_insensitive {
+            normalize_name(a) == normalize_name(b)
+        } else {
+            a.as_ref() == b.as_ref()
+        }
+    }
+
+    pub fn is_admin<T: AsRef<str>>(&'a self, user_id: T) -> bool {
+        self.config
+            .admin_users
+            .iter()
+            .any(|u| u == user_id.as_ref())
+    }
+
+    pub fn plugin<T: AsRef<str>>(&'a self, instance_id: T) -> Option<&'a TheiaPlugin> {
+        self.plugins
+            .iter()
+            .find(|p| p.instance_id == instance_id.as_ref())
+    }
+
+    /// Find the plugin for the `plugin:` namespace of a command, taking
+    /// case-insensitive matching into account if it is enabled.
+    pub fn namespace_plugin<T: AsRef<str>>(&'a self, namespace: T) -> Option<&'a TheiaPlugin> {
+        self.plugins
+            .iter()
+            .find(|p| self.names_match(&p.instance_id, &namespace))
     }
 
     pub fn plugin_with_command<T: AsRef<str>>(&'a self, cmd_name: T) -> Option<&'a TheiaPlugin> {
-        self.plugins.iter().find(|p| {
-            p.config.commands.iter().any(|c| {
-                c.name == cmd_name.as_ref() || c.aliases.iter().any(|a| a == cmd_name.as_ref())
-            })
+        self.resolve_command(cmd_name).map(|(plugin, _)| plugin)
+    }
+
+    /// Find the plugin and command configuration that a command name or
+    /// alias resolves to.
+    pub fn resolve_command<T: AsRef<str>>(
+        &'a self,
+        cmd_name: T,
+    ) -> Option<(&'a TheiaPlugin, &'a TheiaPluginCommandConfig)> {
+        self.registry.lookup(cmd_name).map(|(plugin_idx, cmd_idx)| {
+            let plugin = &self.plugins[plugin_idx];
+            (plugin, &plugin.config.commands[cmd_idx])
         })
     }
 
-    pub fn plugin_names(&'a self) -> Vec<String> {
-        self.plugins.iter().map(|p| p.config.name.clone()).collect()
+    /// Find the command configuration for a command name or alias within a
+    /// single plugin instance, regardless of any collisions.
+    pub fn resolve_plugin_command<P: AsRef<str>, T: AsRef<str>>(
+        &'a self,
+        instance_id: P,
+        cmd_name: T,
+    ) -> Option<(&'a TheiaPlugin, &'a TheiaPluginCommandConfig)> {
+        self.namespace_plugin(instance_id).and_then(|plugin| {
+            plugin
+                .config
+                .commands
+                .iter()
+                .find(|c| c.matches(&cmd_name))
+                .map(|cmdcfg| (plugin, cmdcfg))
+        })
     }
 
-    pub fn plugin_command_summaries(&'a self) -> Vec<(String, String)> {
+    /// Find the plugin and command configuration for a command name or
+    /// alias, which may be qualified with a plugin instance ID as
+    /// `plugin:command`.
+    pub fn resolve_qualified_command<T: AsRef<str>>(
+        &'a self,
+        cmd_name: T,
+    ) -> Option<(&'a TheiaPlugin, &'a TheiaPluginCommandConfig)> {
+        match cmd_name.as_ref().split_once(':') {
+            Some((instance_id, name)) if self.namespace_plugin(instance_id).is_some() => {
+                self.resolve_plugin_command(instance_id, name)
+            }
+
+            _ => self.resolve_command(cmd_name),
+        }
+    }
+
+    /// The name to display for a command in help output.
+    ///
+    /// This is qualified with the plugin instance ID, as `plugin:command`,
+    /// if more than one command wanted the command's name.
+    pub fn command_display_name(
+        &'a self,
+        plugin: &TheiaPlugin,
+        cmdcfg: &TheiaPluginCommandConfig,
+    ) -> String {
+        if self.registry.is_ambiguous(&cmdcfg.name) {
+            format!("{}:{}", plugin.instance_id(), cmdcfg.name)
+        } else {
+            cmdcfg.name.clone()
+        }
+    }
+
+    /// The name to display for a command and a chain of its subcommands,
+    /// such as `plugin:command sub`, as returned by
+    /// [`TheiaPlugin::command_chain`].
+    pub fn command_chain_display_name(
+        &'a self,
+        plugin: &TheiaPlugin,
+        chain: &[&TheiaPluginCommandConfig],
+    ) -> String {
+        let mut path: Vec<String> = chain.iter().map(|c| c.name.clone()).collect();
+        if let Some(cmdcfg) = chain.first() {
+            path[0] = self.command_display_name(plugin, cmdcfg);
+        }
+
+        path.join(" ")
+    }
+
+    /// All commands which can be invoked by their own name, in plugin
+    /// priority order.
+    pub fn registered_commands(&'a self) -> Vec<(&'a TheiaPlugin, &'a TheiaPluginCommandConfig)> {
         self.plugins
             .iter()
-            .map(|p| {
-                p.config
+            .enumerate()
+            .flat_map(|(plugin_idx, plugin)| {
+                plugin
+                    .config
                     .commands
                     .iter()
-                    .filter(|cmdcfg| !cmdcfg.hidden)
-                    .map(|cmdcfg| {
-                        let (help_summary, _) =
-                            parse_command_help(self.prefix(), &cmdcfg.name, &cmdcfg.help);
-                        (String::from(&cmdcfg.name), help_summary)
+                    .enumerate()
+                    .filter(move |(cmd_idx, cmdcfg)| {
+                        self.registry.lookup(&cmdcfg.name) == Some((plugin_idx, *cmd_idx))
                     })
+                    .map(move |(_, cmdcfg)| (plugin, cmdcfg))
+            })
+            .collect()
+    }
+
+    /// Names and aliases of all commands not hidden from global listings,
+    /// including built-in commands.
+    pub fn visible_command_names(&'a self) -> Vec<(String, Vec<String>)> {
+        let mut names: Vec<(String, Vec<String>)> = self
+            .registered_commands()
+            .into_iter()
+            .filter(|(_, cmdcfg)| !cmdcfg.hidden)
+            .map(|(_, cmdcfg)| {
+                let aliases = cmdcfg
+                    .aliases
+                    .iter()
+                    .filter(|a| self.registry.lookup(a) == self.registry.lookup(&cmdcfg.name))
+                    .cloned()
+                    .collect();
+
+                (cmdcfg.name.clone(), aliases)
+            })
+            .collect();
+
+        for builtin in builtin::BUILTIN_COMMANDS.iter() {
+            if !names.iter().any(|(name, _)| name == builtin) {
+                names.push((String::from(*builtin), Vec::new()));
+            }
+        }
+
+        names
+    }
+
+    /// Checks whether a command suggestion may be sent in the given channel,
+    /// recording the current time as the last suggestion if so.
+    pub fn take_suggestion_slot<T: AsRef<str>>(&'a self, channel_id: T) -> bool {
+        let cooldown = Duration::from_secs(self.config.suggestions.cooldown);
+        let mut times = self.suggestion_times.lock().unwrap();
+        let now = Instant::now();
+
+        if let Some(last) = times.get(channel_id.as_ref()) {
+            if now.duration_since(*last) < cooldown {
+                return false;
+            }
+        }
+
+        times.retain(|_, last| now.duration_since(*last) < cooldown);
+        times.insert(String::from(channel_id.as_ref()), now);
+        true
+    }
+
+    /// Record that a plugin instance sent a message.
+    pub fn record_message_owner<M: AsRef<str>, P: AsRef<str>>(
+        &'a self,
+        message_id: M,
+        instance_id: P,
+    ) {
+        self.message_owners
+            .lock()
+            .unwrap()
+            .insert(message_id, instance_id);
+    }
+
+    /// The plugin instance that sent a message, if it is known.
+    pub fn message_owner<M: AsRef<str>>(&'a self, message_id: M) -> Option<&'a TheiaPlugin> {
+        let owners = self.message_owners.lock().unwrap();
+        owners
+            .owner(message_id)
+            .and_then(|instance_id| self.plugin(instance_id))
+    }
+
+    pub fn plugin_names(&'a self) -> Vec<String> {
+        self.plugins.iter().map(|p| p.config.name.clone()).collect()
+    }
+
+    pub fn plugin_instance_ids(&'a self) -> Vec<String> {
+        self.plugins
+            .iter()
+            .map(|p| String::from(p.instance_id()))
+            .collect()
+    }
+
+    pub fn plugin_command_summaries(&'a self) -> Vec<(String, String)> {
+        self.plugins
+            .iter()
+            .flat_map(|p| p.config.commands.iter().map(move |cmdcfg| (p, cmdcfg)))
+            .filter(|(_, cmdcfg)| !cmdcfg.hidden)
+            .map(|(p, cmdcfg)| {
+                let name = self.command_display_name(p, cmdcfg);
+                let (help_summary, _) = parse_command_help(self.prefix(), &name, &cmdcfg.help);
+                (name, help_summary)
             })
-            .flatten()
             .collect()
     }
 }
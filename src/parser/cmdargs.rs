@@ -0,0 +1,256 @@
+//! Command argument schema parser
+
+use crate::error::TheiaArgumentError;
+use crate::plugin::{TheiaPluginCommandArgument, TheiaPluginCommandArgumentType};
+
+use ::serde_json::Value;
+use ::std::collections::HashMap;
+use ::std::str::FromStr;
+
+/// Validate and convert a list of command arguments against a schema.
+///
+/// Returns a map of argument names to converted values. Optional arguments
+/// that were not given are omitted from the map, and variadic arguments are
+/// returned as an array.
+pub fn parse_command_args<A: AsRef<str>>(
+    schema: &[TheiaPluginCommandArgument],
+    args: &[A],
+) -> Result<HashMap<String, Value>, TheiaArgumentError> {
+    let mut parsed: HashMap<String, Value> = HashMap::new();
+    let mut args = args.iter().map(|a| a.as_ref());
+
+    for argdef in schema.iter() {
+        if argdef.variadic {
+            let values = args
+                .by_ref()
+                .map(|a| convert_arg(argdef, a))
+                .collect::<Result<Vec<Value>, TheiaArgumentError>>()?;
+
+            if values.is_empty() && !argdef.optional {
+                return Err(TheiaArgumentError::Missing(argdef.name.clone()));
+            }
+
+            parsed.insert(argdef.name.clone(), Value::Array(values));
+            continue;
+        }
+
+        match args.next() {
+            Some(arg) => {
+                parsed.insert(argdef.name.clone(), convert_arg(argdef, arg)?);
+            }
+
+            None if argdef.optional => {}
+            None => return Err(TheiaArgumentError::Missing(argdef.name.clone())),
+        }
+    }
+
+    if args.next().is_some() {
+        return Err(TheiaArgumentError::TooMany(schema.len()));
+    }
+
+    Ok(parsed)
+}
+
+/// Generate a usage string for a command from its argument schema.
+///
+/// The `prefix` parameter to this function should be the _first_ prefix
+/// in the bot configuration.
+pub fn command_usage<P: AsRef<str>, C: AsRef<str>>(
+    prefix: P,
+    cmdname: C,
+    schema: &[TheiaPluginCommandArgument],
+) -> String {
+    let mut usage = vec![format!("{}{}", prefix.as_ref(), cmdname.as_ref())];
+    for argdef in schema.iter() {
+        let name = if argdef.variadic {
+            format!("{}...", argdef.name)
+        } else {
+            argdef.name.clone()
+        };
+
+        if argdef.optional {
+            usage.push(format!("[{}]", name));
+        } else {
+            usage.push(format!("<{}>", name));
+        }
+    }
+
+    usage.join(" ")
+}
+
+fn convert_arg(
+    argdef: &TheiaPluginCommandArgument,
+    arg: &str,
+) -> Result<Value, TheiaArgumentError> {
+    let converted = match argdef.kind {
+        TheiaPluginCommandArgumentType::String => Some(Value::from(arg)),
+        TheiaPluginCommandArgumentType::Integer => i64::from_str(arg).ok().map(Value::from),
+        TheiaPluginCommandArgumentType::User => parse_mention(arg, &["<@!", "<@"]),
+        TheiaPluginCommandArgumentType::Channel => parse_mention(arg, &["<#"]),
+        TheiaPluginCommandArgumentType::Role => parse_mention(arg, &["<@&"]),
+        TheiaPluginCommandArgumentType::Duration => parse_duration(arg).map(Value::from),
+    };
+
+    converted.ok_or_else(|| TheiaArgumentError::Invalid {
+        name: argdef.name.clone(),
+        value: String::from(arg),
+        expected: String::from(argdef.kind.describe()),
+    })
+}
+
+/// Parse a mention with one of the given opening sequences, or a bare ID.
+fn parse_mention(arg: &str, openers: &[&str]) -> Option<Value> {
+    let id = openers
+        .iter()
+        .find_map(|o| arg.strip_prefix(o).and_then(|a| a.strip_suffix('>')))
+        .unwrap_or(arg);
+
+    u64::from_str(id).ok().map(|id| Value::from(id.to_string()))
+}
+
+/// Parse a duration such as `1h30m` into a number of seconds.
+///
+/// A bare number is treated as a number of seconds.
+fn parse_duration(arg: &str) -> Option<u64> {
+    if let Ok(secs) = u64::from_str(arg) {
+        return Some(secs);
+    }
+
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    for c in arg.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let multiplier: u64 = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            'w' => 60 * 60 * 24 * 7,
+            _ => return None,
+        };
+
+        let value = u64::from_str(&digits).ok()?;
+        total = total.checked_add(value.checked_mul(multiplier)?)?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() || arg.is_empty() {
+        return None;
+    }
+
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argdef(
+        name: &str,
+        kind: TheiaPluginCommandArgumentType,
+        optional: bool,
+        variadic: bool,
+    ) -> TheiaPluginCommandArgument {
+        TheiaPluginCommandArgument {
+            name: String::from(name),
+            kind,
+            optional,
+            variadic,
+        }
+    }
+
+    #[test]
+    fn converts_typed_arguments() {
+        let schema = vec![
+            argdef("who", TheiaPluginCommandArgumentType::User, false, false),
+            argdef(
+                "where",
+                TheiaPluginCommandArgumentType::Channel,
+                false,
+                false,
+            ),
+            argdef(
+                "count",
+                TheiaPluginCommandArgumentType::Integer,
+                false,
+                false,
+            ),
+            argdef(
+                "for",
+                TheiaPluginCommandArgumentType::Duration,
+                false,
+                false,
+            ),
+        ];
+
+        let parsed = parse_command_args(&schema, &["<@!1234>", "<#5678>", "-3", "1h30m"]).unwrap();
+        assert_eq!(Value::from("1234"), parsed["who"]);
+        assert_eq!(Value::from("5678"), parsed["where"]);
+        assert_eq!(Value::from(-3), parsed["count"]);
+        assert_eq!(Value::from(5400), parsed["for"]);
+    }
+
+    #[test]
+    fn optional_and_variadic() {
+        let schema = vec![
+            argdef("role", TheiaPluginCommandArgumentType::Role, false, false),
+            argdef("reason", TheiaPluginCommandArgumentType::String, true, true),
+        ];
+
+        let parsed = parse_command_args(&schema, &["<@&42>", "being", "rude"]).unwrap();
+        assert_eq!(Value::from("42"), parsed["role"]);
+        assert_eq!(
+            Value::Array(vec![Value::from("being"), Value::from("rude")]),
+            parsed["reason"]
+        );
+
+        let parsed = parse_command_args(&schema, &["42"]).unwrap();
+        assert_eq!(Value::Array(vec![]), parsed["reason"]);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let schema = vec![argdef(
+            "count",
+            TheiaPluginCommandArgumentType::Integer,
+            false,
+            false,
+        )];
+
+        assert_eq!(
+            Err(TheiaArgumentError::Missing(String::from("count"))),
+            parse_command_args::<&str>(&schema, &[])
+        );
+        assert_eq!(
+            Err(TheiaArgumentError::TooMany(1)),
+            parse_command_args(&schema, &["1", "2"])
+        );
+        assert!(matches!(
+            parse_command_args(&schema, &["one"]),
+            Err(TheiaArgumentError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn usage_from_schema() {
+        let schema = vec![
+            argdef("user", TheiaPluginCommandArgumentType::User, false, false),
+            argdef(
+                "count",
+                TheiaPluginCommandArgumentType::Integer,
+                true,
+                false,
+            ),
+            argdef("rest", TheiaPluginCommandArgumentType::String, true, true),
+        ];
+
+        assert_eq!(
+            "t;test <user> [count] [rest...]",
+            command_usage("t;", "test", &schema)
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! Command help string parser
 
+use crate::plugin::TheiaPluginCommandConfig;
+
 /// Parse a command help string (from a plugin config) into it's parts.
 ///
 /// Returns a tuple of the command summary, and potentially any further
@@ -55,6 +57,44 @@ pub fn parse_command_help<P: AsRef<str>, C: AsRef<str>, H: AsRef<str>>(
     (summary, details)
 }
 
+/// Render the full help text for a command.
+///
+/// This includes the summary and details from the command's help string,
+/// followed by the summaries of any subcommands that are not hidden.
+/// `cmdname` should include the names of any parent commands, for
+/// subcommands.
+pub fn render_command_help<P: AsRef<str>, C: AsRef<str>>(
+    prefix: P,
+    cmdname: C,
+    cmdcfg: &TheiaPluginCommandConfig,
+) -> String {
+    let (mut help_s, help_details) = parse_command_help(&prefix, &cmdname, &cmdcfg.help);
+    if let Some(details) = help_details {
+        help_s = format!("{}\n\n{}", help_s, details);
+    }
+
+    let subcommands: Vec<String> = cmdcfg
+        .subcommands
+        .iter()
+        .filter(|subcmd| !subcmd.hidden)
+        .map(|subcmd| {
+            let subname = format!("{} {}", cmdname.as_ref(), subcmd.name);
+            let (summary, _) = parse_command_help(&prefix, &subname, &subcmd.help);
+            if summary.is_empty() {
+                format!("`{}{}`", prefix.as_ref(), subname)
+            } else {
+                summary
+            }
+        })
+        .collect();
+
+    if !subcommands.is_empty() {
+        help_s = format!("{}\n\nSubcommands:\n{}", help_s, subcommands.join("\n"));
+    }
+
+    help_s
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +131,35 @@ mod tests {
             details
         );
     }
+
+    #[test]
+    fn subcommand_help() {
+        let cmdcfg: TheiaPluginCommandConfig = ::toml::from_str(
+            r#"
+                name = "config"
+                help = "`%!` - manage configuration"
+
+                [[subcommand]]
+                name = "get"
+                help = "`%! <key>` - get a value"
+
+                [[subcommand]]
+                name = "debug"
+                hidden = true
+
+                [[subcommand]]
+                name = "set"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            "`t;config` - manage configuration\n\nSubcommands:\n`t;config get <key>` - get a value\n`t;config set`",
+            render_command_help("t;", "config", &cmdcfg)
+        );
+        assert_eq!(
+            "`t;config get <key>` - get a value",
+            render_command_help("t;", "config get", &cmdcfg.subcommands[0])
+        );
+    }
 }
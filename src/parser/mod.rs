@@ -1,3 +1,6 @@
-//! Various parsers, including for command help strings.
+//! Various parsers, including for command arguments, help strings and
+//! argument schemas.
 
+pub mod cmdargs;
 pub mod cmdhelp;
+pub mod tokenize;
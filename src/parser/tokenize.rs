@@ -0,0 +1,258 @@
+//! Command argument tokenizer
+
+use crate::error::TheiaParseError;
+
+/// Split a command string into arguments.
+///
+/// Arguments are separated by whitespace, which can be included in an
+/// argument by quoting or escaping it:
+///
+/// - `"double quotes"` allow `\"` and `\\` escapes, and any other
+///   backslash is kept as-is;
+/// - `'single quotes'` are entirely literal, but only open at the start of
+///   an argument, and only close at a `'` followed by whitespace or the end
+///   of the input, so that apostrophes (as in `don't`) are kept as-is;
+/// - outside of quotes, a backslash escapes the following character;
+/// - inline code (`` `code` ``) and code blocks (` ``` `) are kept verbatim,
+///   including the backticks, as part of a single argument.
+///
+/// Newlines inside quotes and code are preserved. `offset` is added to the
+/// position of any returned error, so that it points into the full input
+/// when `input` is a substring of it.
+pub fn tokenize(input: &str, offset: usize) -> Result<Vec<String>, TheiaParseError> {
+    Ok(tokenize_with_ends(input, offset)?
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect())
+}
+
+/// Split a command string into arguments, as with [`tokenize`], returning
+/// the character position in `input` just after the end of each argument.
+pub fn tokenize_with_ends(
+    input: &str,
+    offset: usize,
+) -> Result<Vec<(String, usize)>, TheiaParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens: Vec<(String, usize)> = Vec::new();
+    let mut current: Option<String> = None;
+    let mut idx = 0;
+
+    // A single quote only closes before whitespace or the end of the input
+    let closes_single =
+        |i: usize| chars[i] == '\'' && chars.get(i + 1).map_or(true, |c| c.is_whitespace());
+
+    while idx < chars.len() {
+        let c = chars[idx];
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(token) = current.take() {
+                    tokens.push((token, idx));
+                }
+
+                idx += 1;
+            }
+
+            '\'' if current.is_some() || !(idx + 1..chars.len()).any(closes_single) => {
+                current.get_or_insert_with(String::new).push(c);
+                idx += 1;
+            }
+
+            '"' | '\'' => {
+                let start = idx;
+                let token = current.get_or_insert_with(String::new);
+                idx += 1;
+
+                loop {
+                    match chars.get(idx) {
+                        None => {
+                            return Err(TheiaParseError::UnterminatedQuote {
+                                quote: c,
+                                position: offset + start,
+                            });
+                        }
+
+                        Some(&q) if q == c && (c == '"' || closes_single(idx)) => {
+                            idx += 1;
+                            break;
+                        }
+
+                        Some('\\')
+                            if c == '"' && matches!(chars.get(idx + 1), Some('"' | '\\')) =>
+                        {
+                            token.push(chars[idx + 1]);
+                            idx += 2;
+                        }
+
+                        Some(&other) => {
+                            token.push(other);
+                            idx += 1;
+                        }
+                    }
+                }
+            }
+
+            '`' => {
+                let start = idx;
+                let fence_len = chars[idx..].iter().take_while(|&&b| b == '`').count();
+                let fence: String = "`".repeat(fence_len);
+                let token = current.get_or_insert_with(String::new);
+                token.push_str(&fence);
+                idx += fence_len;
+
+                loop {
+                    if idx >= chars.len() {
+                        return Err(TheiaParseError::UnterminatedCode {
+                            fence,
+                            position: offset + start,
+                        });
+                    }
+
+                    let run = chars[idx..].iter().take_while(|&&b| b == '`').count();
+                    if run == fence_len {
+                        token.push_str(&fence);
+                        idx += run;
+                        break;
+                    } else if run > 0 {
+                        token.push_str(&"`".repeat(run));
+                        idx += run;
+                    } else {
+                        token.push(chars[idx]);
+                        idx += 1;
+                    }
+                }
+            }
+
+            '\\' => {
+                let token = current.get_or_insert_with(String::new);
+                match chars.get(idx + 1) {
+                    Some(&escaped) => {
+                        token.push(escaped);
+                        idx += 2;
+                    }
+
+                    None => {
+                        token.push('\\');
+                        idx += 1;
+                    }
+                }
+            }
+
+            _ => {
+                current.get_or_insert_with(String::new).push(c);
+                idx += 1;
+            }
+        }
+    }
+
+    if let Some(token) = current.take() {
+        tokens.push((token, chars.len()));
+    }
+
+    Ok(tokens)
+}
+
+/// Render the line of `input` containing `position`, with a caret pointing
+/// at that position, for showing alongside a parse error.
+pub fn highlight_position(input: &str, position: usize) -> String {
+    let mut line_start = 0;
+    let mut lines = input.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let len = line.chars().count();
+        if position <= line_start + len || lines.peek().is_none() {
+            let column = position.saturating_sub(line_start);
+            return format!("```\n{}\n{}^\n```", line, " ".repeat(column));
+        }
+
+        line_start += len + 1;
+    }
+
+    String::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<String> {
+        tokenize(input, 0).unwrap()
+    }
+
+    #[test]
+    fn whitespace_split() {
+        assert_eq!(vec!["one", "two", "three"], tokens(" one  two\nthree "));
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        assert_eq!(
+            vec!["hello world", "it is", "a \"b\" \\d", "x y", ""],
+            tokens(r#""hello world" 'it is' "a \"b\" \d" x\ y """#)
+        );
+    }
+
+    #[test]
+    fn apostrophes_kept() {
+        assert_eq!(vec!["say", "don't", "'tis"], tokens("say don't 'tis"));
+        assert_eq!(vec!["rock'n'roll", "a b"], tokens("rock'n'roll 'a b'"));
+        assert_eq!(
+            vec!["say", "'tis", "fine,", "isn't", "it"],
+            tokens("say 'tis fine, isn't it")
+        );
+        assert_eq!(vec!["it's here", "x"], tokens("'it's here' x"));
+    }
+
+    #[test]
+    fn newlines_preserved_in_quotes() {
+        assert_eq!(vec!["one\ntwo", "three"], tokens("\"one\ntwo\" three"));
+    }
+
+    #[test]
+    fn code_kept_verbatim() {
+        assert_eq!(
+            vec!["eval", "```rs\nlet x = \"a b\";\n```", "`a ' b`"],
+            tokens("eval ```rs\nlet x = \"a b\";\n``` `a ' b`")
+        );
+    }
+
+    #[test]
+    fn token_ends() {
+        assert_eq!(
+            vec![(String::from("echo"), 4), (String::from("a b"), 12)],
+            tokenize_with_ends("echo   \"a b\"  ", 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        assert_eq!(
+            Err(TheiaParseError::UnterminatedQuote {
+                quote: '"',
+                position: 6,
+            }),
+            tokenize("say \"hello", 2)
+        );
+    }
+
+    #[test]
+    fn unterminated_code() {
+        assert_eq!(
+            Err(TheiaParseError::UnterminatedCode {
+                fence: String::from("```"),
+                position: 5,
+            }),
+            tokenize("eval ```\nhello``", 0)
+        );
+    }
+
+    #[test]
+    fn highlight() {
+        assert_eq!(
+            "```\nt;say \"hi\n      ^\n```",
+            highlight_position("t;say \"hi", 6)
+        );
+        assert_eq!(
+            "```\nsecond\n  ^\n```",
+            highlight_position("first\nsecond", 8)
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! File attachments sent by plugins.
+
+use crate::error::TheiaPluginMessageError;
+use ::serde::{Deserialize, Serialize};
+use ::std::fs;
+use ::std::path::{Component, Path};
+
+/// Maximum number of attachments in a single message.
+pub const MAX_ATTACHMENTS: usize = 10;
+
+/// Maximum total size of all attachments in a single message, in bytes.
+pub const MAX_ATTACHMENT_SIZE: usize = 8 * 1024 * 1024;
+
+/// A file attached to a message sent by a plugin.
+///
+/// The file contents are given either as base64 `data`, or as a `path`
+/// relative to the plugin directory, which must not lead outside of it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TheiaPluginAttachment {
+    /// Filename shown in Discord.
+    pub filename: String,
+
+    /// MIME type of the file.
+    ///
+    /// Discord picks how to display a file from its filename, so this is
+    /// only used to add an extension to filenames without one.
+    #[serde(default)]
+    pub content_type: Option<String>,
+
+    /// Base64-encoded file contents.
+    #[serde(default)]
+    pub data: Option<String>,
+
+    /// Path to the file, relative to the plugin directory.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl TheiaPluginAttachment {
+    /// The filename to upload the attachment as.
+    pub fn upload_filename(&self) -> String {
+        if Path::new(&self.filename).extension().is_some() {
+            return self.filename.clone();
+        }
+
+        let extension = match self.content_type.as_deref() {
+            Some("image/png") => "png",
+            Some("image/jpeg") => "jpg",
+            Some("image/gif") => "gif",
+            Some("image/webp") => "webp",
+            Some("text/plain") => "txt",
+            Some("text/csv") => "csv",
+            Some("application/json") => "json",
+            Some("application/pdf") => "pdf",
+            _ => return self.filename.clone(),
+        };
+
+        format!("{}.{}", self.filename, extension)
+    }
+
+    /// Load the contents of the attachment.
+    pub fn load(&self, plugin_dir: &Path) -> Result<Vec<u8>, TheiaPluginMessageError> {
+        if self.filename.trim().is_empty() || self.filename.contains(['/', '\\']) {
+            return Err(TheiaPluginMessageError::InvalidFilename(
+                self.filename.clone(),
+            ));
+        }
+
+        match (&self.data, &self.path) {
+            (Some(data), None) => ::base64::decode(data.trim())
+                .map_err(|_| TheiaPluginMessageError::InvalidAttachmentData(self.filename.clone())),
+
+            (None, Some(path)) => {
+                let unreadable = |reason: String| TheiaPluginMessageError::AttachmentUnreadable {
+                    path: path.clone(),
+                    reason,
+                };
+
+                // Reject anything that could lead outside of the plugin
+                // directory, then check again after resolving symlinks
+                let relative = Path::new(path);
+                if relative
+                    .components()
+                    .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+                {
+                    return Err(TheiaPluginMessageError::AttachmentPathEscape(path.clone()));
+                }
+
+                let plugin_dir = plugin_dir
+                    .canonicalize()
+                    .map_err(|e| unreadable(e.to_string()))?;
+                let full = plugin_dir
+                    .join(relative)
+                    .canonicalize()
+                    .map_err(|e| unreadable(e.to_string()))?;
+                if !full.starts_with(&plugin_dir) {
+                    return Err(TheiaPluginMessageError::AttachmentPathEscape(path.clone()));
+                }
+
+                let size = fs::metadata(&full)
+                    .map_err(|e| unreadable(e.to_string()))?
+                    .len() as usize;
+                if size > MAX_ATTACHMENT_SIZE {
+                    return Err(TheiaPluginMessageError::AttachmentsTooLarge {
+                        size,
+                        limit: MAX_ATTACHMENT_SIZE,
+                    });
+                }
+
+                fs::read(&full).map_err(|e| unreadable(e.to_string()))
+            }
+
+            _ => Err(TheiaPluginMessageError::AttachmentSource(
+                self.filename.clone(),
+            )),
+        }
+    }
+
+    /// Load a list of attachments for a single message, checking them
+    /// against Discord's limits.
+    ///
+    /// Returns the upload filename and contents of each attachment.
+    pub fn load_all(
+        attachments: &[Self],
+        plugin_dir: &Path,
+    ) -> Result<Vec<(String, Vec<u8>)>, TheiaPluginMessageError> {
+        if attachments.len() > MAX_ATTACHMENTS {
+            return Err(TheiaPluginMessageError::TooManyAttachments {
+                count: attachments.len(),
+                limit: MAX_ATTACHMENTS,
+            });
+        }
+
+        let mut files = Vec::new();
+        let mut size = 0;
+        for attachment in attachments.iter() {
+            let data = attachment.load(plugin_dir)?;
+            size += data.len();
+            if size > MAX_ATTACHMENT_SIZE {
+                return Err(TheiaPluginMessageError::AttachmentsTooLarge {
+                    size,
+                    limit: MAX_ATTACHMENT_SIZE,
+                });
+            }
+
+            files.push((attachment.upload_filename(), data));
+        }
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(filename: &str, data: Option<&str>, path: Option<&str>) -> TheiaPluginAttachment {
+        TheiaPluginAttachment {
+            filename: String::from(filename),
+            content_type: None,
+            data: data.map(String::from),
+            path: path.map(String::from),
+        }
+    }
+
+    #[test]
+    fn base64_data() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(
+            Ok(b"hello".to_vec()),
+            attachment("a.txt", Some("aGVsbG8="), None).load(dir)
+        );
+        assert_eq!(
+            Err(TheiaPluginMessageError::InvalidAttachmentData(
+                String::from("a.txt")
+            )),
+            attachment("a.txt", Some("not base64!"), None).load(dir)
+        );
+        assert_eq!(
+            Err(TheiaPluginMessageError::AttachmentSource(String::from(
+                "a.txt"
+            ))),
+            attachment("a.txt", Some("aGVsbG8="), Some("Cargo.toml")).load(dir)
+        );
+        assert_eq!(
+            Err(TheiaPluginMessageError::InvalidFilename(String::from(
+                "../a.txt"
+            ))),
+            attachment("../a.txt", Some("aGVsbG8="), None).load(dir)
+        );
+    }
+
+    #[test]
+    fn paths_stay_in_plugin_dir() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        assert!(attachment("lib.rs", None, Some("./lib.rs"))
+            .load(&dir)
+            .is_ok());
+
+        for path in ["../Cargo.toml", "/etc/passwd", "plugin/../../Cargo.toml"] {
+            assert_eq!(
+                Err(TheiaPluginMessageError::AttachmentPathEscape(String::from(
+                    path
+                ))),
+                attachment("a", None, Some(path)).load(&dir)
+            );
+        }
+    }
+
+    #[test]
+    fn upload_filenames() {
+        let mut chart = attachment("chart", Some(""), None);
+        assert_eq!("chart", chart.upload_filename());
+
+        chart.content_type = Some(String::from("image/png"));
+        assert_eq!("chart.png", chart.upload_filename());
+
+        chart.filename = String::from("chart.jpeg");
+        assert_eq!("chart.jpeg", chart.upload_filename());
+    }
+}
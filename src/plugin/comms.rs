@@ -1,8 +1,22 @@
+use super::attachment::{TheiaPluginAttachment, MAX_ATTACHMENTS};
+use super::embed::TheiaPluginEmbed;
+use super::TheiaPluginCapability;
+use crate::audit::TheiaAuditEntry;
+use crate::config::TheiaLongMessageConfig;
+use crate::discord::mentions::TheiaAllowedMentions;
+use crate::discord::reaction::TheiaDiscordReaction;
+use crate::discord::split::{split_content, CONTENT_FILENAME, MAX_MESSAGE_LENGTH};
+use crate::error::TheiaPluginMessageError;
 use crate::prelude::*;
 use ::serde::{Deserialize, Serialize};
 use ::serde_json::Value;
-use ::serenity::model::id::{ChannelId, MessageId};
+use ::serenity::builder::CreateEmbed;
+use ::serenity::http::AttachmentType;
+use ::serenity::model::channel::ReactionType;
+use ::serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
+use ::std::borrow::Cow;
 use ::std::collections::HashMap;
+use ::std::convert::TryFrom;
 use ::std::str::FromStr;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,7 +29,14 @@ pub enum TheiaPluginOutgoingMessage {
         #[serde(rename = "this_shard")]
         shard_id: u64,
         total_shards: u64,
+
+        /// Names of the loaded plugins, which repeat for plugins loaded as
+        /// several instances
         plugins: Vec<String>,
+
+        /// Instance IDs of the loaded plugins, in the same order as
+        /// `plugins`
+        plugin_instances: Vec<String>,
         commands: Vec<(String, String)>,
     },
 
@@ -23,6 +44,9 @@ pub enum TheiaPluginOutgoingMessage {
         #[serde(rename = "plugin-name")]
         plugin_name: String,
 
+        #[serde(rename = "plugin-instance")]
+        plugin_instance: String,
+
         #[serde(rename = "config-plugin")]
         plugin_cfg: HashMap<String, Value>,
 
@@ -37,6 +61,42 @@ pub enum TheiaPluginOutgoingMessage {
     CommandInvoke {
         message: TheiaDiscordMessage,
     },
+
+    /// All messages for the run have been sent to an interactive plugin,
+    /// whose stdin is left open for acknowledgements.
+    EndOfInput {},
+
+    /// Acknowledgement of a message sent or edited on behalf of an
+    /// interactive plugin, for a request carrying a nonce.
+    MessageSent {
+        nonce: String,
+        message_id: String,
+        channel_id: String,
+    },
+
+    /// Acknowledgement of any other successful request carrying a nonce
+    /// from an interactive plugin.
+    ActionDone {
+        nonce: String,
+    },
+
+    /// A request carrying a nonce from an interactive plugin failed.
+    ActionFailed {
+        nonce: String,
+        error: String,
+    },
+
+    /// A reaction was added to a message the plugin sent, if the plugin
+    /// subscribed to `reaction-add` events.
+    ReactionAdd {
+        reaction: TheiaDiscordReaction,
+    },
+
+    /// A reaction was removed from a message the plugin sent, if the plugin
+    /// subscribed to `reaction-remove` events.
+    ReactionRemove {
+        reaction: TheiaDiscordReaction,
+    },
 }
 
 impl TheiaPluginOutgoingMessage {
@@ -51,58 +111,1265 @@ impl TheiaPluginOutgoingMessage {
             shard_id: ctx.shard_id,
             total_shards: theia.config.shard_count,
             plugins: theia.plugin_names(),
+            plugin_instances: theia.plugin_instance_ids(),
             commands: theia.plugin_command_summaries(),
         }
     }
 
+    /// The invocation ID of the command invocation carried by this message,
+    /// if any.
+    pub fn invoke_id(&self) -> Option<&str> {
+        match self {
+            Self::CommandInvoke { message } => message
+                .command_invocation
+                .as_ref()
+                .and_then(|cmd| cmd.invoke_id.as_deref()),
+            _ => None,
+        }
+    }
+
+    /// The channel of the command invocation or event carried by this
+    /// message, if any.
+    pub fn event_channel_id(&self) -> Option<&str> {
+        match self {
+            Self::CommandInvoke { message } => Some(&message.channel_id),
+            Self::ReactionAdd { reaction } | Self::ReactionRemove { reaction } => {
+                Some(&reaction.channel_id)
+            }
+
+            _ => None,
+        }
+    }
+
+    /// The guild of the command invocation or event carried by this message,
+    /// if any.
+    pub fn event_guild_id(&self) -> Option<&str> {
+        match self {
+            Self::CommandInvoke { message } => message.guild_id.as_deref(),
+            Self::ReactionAdd { reaction } | Self::ReactionRemove { reaction } => {
+                reaction.guild_id.as_deref()
+            }
+
+            _ => None,
+        }
+    }
+
+    /// The message containing the command invocation carried by this
+    /// message, if any.
+    pub fn command_message(&self) -> Option<&TheiaDiscordMessage> {
+        match self {
+            Self::CommandInvoke { message } => Some(message),
+            _ => None,
+        }
+    }
+
     pub async fn plugin_config<'p>(plugin: &'p TheiaPlugin) -> Self {
         Self::PluginConfig {
             plugin_name: String::from(plugin.name()),
+            plugin_instance: String::from(plugin.instance_id()),
             plugin_cfg: plugin.config.cfgdata.clone(),
             bot_cfg: plugin.cfgdata.clone(),
         }
     }
 }
 
+/// Discord error code for a user not accepting direct messages from the bot.
+const DISCORD_CANNOT_MESSAGE_USER: isize = 50007;
+
+/// Maximum number of days of messages a ban can delete.
+const MAX_BAN_DELETE_DAYS: u8 = 7;
+
+/// Maximum duration of a timeout, in seconds.
+const MAX_TIMEOUT_DURATION: u64 = 28 * 24 * 60 * 60;
+
+/// Maximum number of messages deleted in a single bulk delete.
+const MAX_BULK_DELETE: usize = 100;
+
+/// State shared by the messages received from a single run of a plugin.
+#[derive(Debug)]
+pub struct TheiaPluginInvocation<'a> {
+    /// The plugin that was run.
+    pub plugin: &'a TheiaPlugin,
+
+    /// The message containing the command invocation the plugin was run
+    /// for, if any.
+    pub origin: Option<&'a TheiaDiscordMessage>,
+
+    /// The channel the plugin was invoked in, or the channel of the event
+    /// the plugin was run for, if any.
+    pub channel_id: Option<ChannelId>,
+
+    /// The guild the plugin was invoked in, or the guild of the event the
+    /// plugin was run for, if any.
+    pub guild_id: Option<GuildId>,
+
+    /// Messages sent during this run, keyed by the handle the plugin gave
+    /// them.
+    handles: HashMap<String, (ChannelId, MessageId)>,
+
+    /// Number of messages sent during this run.
+    sent: usize,
+}
+
+impl<'a> TheiaPluginInvocation<'a> {
+    /// Create the state for a run of a plugin with a list of messages.
+    pub fn new(plugin: &'a TheiaPlugin, msgs: &'a [TheiaPluginOutgoingMessage]) -> Self {
+        Self {
+            plugin,
+            origin: msgs.iter().find_map(|m| m.command_message()),
+            channel_id: msgs
+                .iter()
+                .find_map(|m| m.event_channel_id())
+                .and_then(|id| u64::from_str(id).ok())
+                .map(ChannelId::from),
+            guild_id: msgs
+                .iter()
+                .find_map(|m| m.event_guild_id())
+                .and_then(|id| u64::from_str(id).ok())
+                .map(GuildId::from),
+            handles: HashMap::new(),
+            sent: 0,
+        }
+    }
+
+    fn error(&self, why: TheiaPluginMessageError) -> TheiaError {
+        TheiaError::PluginMessage(String::from(self.plugin.instance_id()), why)
+    }
+
+    /// Parse a Discord ID given by the plugin.
+    fn id(&self, id: &str) -> Result<u64, TheiaError> {
+        u64::from_str(id)
+            .map_err(|_| self.error(TheiaPluginMessageError::InvalidId(String::from(id))))
+    }
+
+    /// Record a message sent by the plugin during this run.
+    async fn record_sent(&self, ctx: &SerenityContext, sent: &SerenityDiscordMessage) {
+        let data = ctx.data.read().await;
+        if let Some(theia) = data.get::<TheiaContainer>() {
+            theia.record_message_owner(sent.id.to_string(), self.plugin.instance_id());
+        }
+    }
+
+    /// Send a message on behalf of the plugin, after checking it against
+    /// Discord's limits.
+    ///
+    /// Content too long for one message is split into several, and the last
+    /// one sent is returned.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_message(
+        &mut self,
+        ctx: &SerenityContext,
+        channel_id: ChannelId,
+        in_reply_to: Option<MessageId>,
+        content: &str,
+        embeds: &[TheiaPluginEmbed],
+        attachments: &[TheiaPluginAttachment],
+        allowed_mentions: &Option<TheiaAllowedMentions>,
+        handle: &Option<String>,
+    ) -> Result<SerenityDiscordMessage, TheiaError> {
+        if content.is_empty() && embeds.is_empty() && attachments.is_empty() {
+            return Err(self.error(TheiaPluginMessageError::EmptyMessage));
+        }
+
+        if !embeds.is_empty() {
+            self.require(TheiaPluginCapability::Embeds)?;
+        }
+
+        TheiaPluginEmbed::validate_all(embeds).map_err(|e| self.error(e))?;
+        // Attachments may be read from disk, so are loaded off the runtime
+        let mut files = if attachments.is_empty() {
+            Vec::new()
+        } else {
+            let (attachments, plugin_dir) = (attachments.to_vec(), self.plugin.path.clone());
+            ::tokio::task::spawn_blocking(move || {
+                TheiaPluginAttachment::load_all(&attachments, &plugin_dir)
+            })
+            .await
+            .map_err(|_| TheiaError::UnknownError)?
+            .map_err(|e| self.error(e))?
+        };
+
+        let mentions = match allowed_mentions {
+            Some(mentions) => {
+                self.require(TheiaPluginCapability::Mentions)?;
+                if mentions.everyone {
+                    self.require(TheiaPluginCapability::MentionEveryone)?;
+                }
+
+                mentions.validate().map_err(|e| self.error(e))?;
+                mentions.clone()
+            }
+
+            None => Default::default(),
+        };
+
+        let limits = self.long_messages(ctx).await;
+        let mut chunks = split_content(content, MAX_MESSAGE_LENGTH);
+        if self.sent + chunks.len() > limits.max_messages
+            && chunks.len() > 1
+            && limits.attachment_fallback
+        {
+            files.push((String::from(CONTENT_FILENAME), content.as_bytes().to_vec()));
+            if files.len() > MAX_ATTACHMENTS {
+                return Err(self.error(TheiaPluginMessageError::TooManyAttachments {
+                    count: files.len(),
+                    limit: MAX_ATTACHMENTS,
+                }));
+            }
+
+            chunks = vec![String::new()];
+        }
+
+        if self.sent + chunks.len() > limits.max_messages {
+            return Err(self.error(TheiaPluginMessageError::TooManyMessages {
+                limit: limits.max_messages,
+            }));
+        }
+
+        // Only the first part replies, and only the last carries embeds and
+        // files
+        let last = chunks.len() - 1;
+        let mut sent = None;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let msg = channel_id
+                .send_message(&ctx.http, |m| {
+                    if let Some(mid) = in_reply_to.filter(|_| i == 0) {
+                        m.reference_message((channel_id, mid));
+                    }
+
+                    if !chunk.is_empty() {
+                        m.content(chunk);
+                    }
+
+                    m.allowed_mentions(|am| mentions.apply(am));
+
+                    if i == last {
+                        m.set_embeds(embeds.iter().map(CreateEmbed::from).collect());
+                        m.add_files(files.iter().map(|(filename, data)| AttachmentType::Bytes {
+                            data: Cow::Borrowed(data),
+                            filename: filename.clone(),
+                        }));
+                    }
+
+                    m
+                })
+                .await?;
+
+            self.sent += 1;
+            self.record_sent(ctx, &msg).await;
+            sent = Some(msg);
+        }
+
+        let sent = sent.ok_or(TheiaError::UnknownError)?;
+        if let Some(handle) = handle {
+            self.handles
+                .insert(handle.clone(), (sent.channel_id, sent.id));
+        }
+
+        Ok(sent)
+    }
+
+    /// Limits on messages sent by the plugin, from the bot configuration.
+    async fn long_messages(&self, ctx: &SerenityContext) -> TheiaLongMessageConfig {
+        let data = ctx.data.read().await;
+        data.get::<TheiaContainer>()
+            .map(|theia| theia.config.long_messages.clone())
+            .unwrap_or_default()
+    }
+
+    /// Check that the plugin may use a capability, logging the reason if
+    /// not.
+    fn require(&self, capability: TheiaPluginCapability) -> Result<(), TheiaError> {
+        self.plugin.check_capability(capability).map_err(|why| {
+            warn!(
+                "Rejected action from plugin {:?}: {}",
+                self.plugin.instance_id(),
+                why
+            );
+
+            self.error(why)
+        })
+    }
+
+    /// Check that a message referenced by ID was sent by this plugin
+    /// instance, rather than by another plugin or the bot itself.
+    ///
+    /// Only the bot's recent messages have their sender recorded.
+    async fn require_own_message(
+        &self,
+        ctx: &SerenityContext,
+        message_id: MessageId,
+    ) -> Result<(), TheiaError> {
+        let data = ctx.data.read().await;
+        let owned = data
+            .get::<TheiaContainer>()
+            .and_then(|theia| theia.message_owner(message_id.to_string()))
+            .is_some_and(|owner| owner.instance_id() == self.plugin.instance_id());
+
+        if !owned {
+            return Err(self.error(TheiaPluginMessageError::NotOwnMessage(
+                message_id.to_string(),
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check that the plugin may act in a channel, which requires the
+    /// `send-anywhere` capability unless it is the channel the plugin was
+    /// invoked in.
+    fn require_channel(&self, channel_id: ChannelId) -> Result<(), TheiaError> {
+        if self.channel_id == Some(channel_id) {
+            return Ok(());
+        }
+
+        self.require(TheiaPluginCapability::SendAnywhere)
+    }
+
+    /// Checks that a channel is in the guild the plugin was run for, which
+    /// otherwise requires the `cross-guild` capability.
+    async fn require_channel_guild(
+        &self,
+        ctx: &SerenityContext,
+        channel_id: ChannelId,
+    ) -> Result<(), TheiaError> {
+        if self.channel_id == Some(channel_id) {
+            return Ok(());
+        }
+
+        let guild_id = channel_id
+            .to_channel(ctx)
+            .await?
+            .guild()
+            .map(|c| c.guild_id);
+        if guild_id != self.guild_id {
+            self.require(TheiaPluginCapability::CrossGuild)?;
+        }
+
+        Ok(())
+    }
+
+    /// The guild for a moderation action, defaulting to the guild the
+    /// plugin was run for. Any other guild requires the `cross-guild`
+    /// capability.
+    fn guild_id(&self, guild_id: &Option<String>) -> Result<GuildId, TheiaError> {
+        match (guild_id, self.guild_id) {
+            (Some(guild_id), run_for) => {
+                let guild_id = GuildId::from(self.id(guild_id)?);
+                if run_for != Some(guild_id) {
+                    self.require(TheiaPluginCapability::CrossGuild)?;
+                }
+
+                Ok(guild_id)
+            }
+
+            (None, Some(run_for)) => Ok(run_for),
+            (None, None) => Err(self.error(TheiaPluginMessageError::NoGuild)),
+        }
+    }
+
+    fn audit_entry<A: AsRef<str>, T: AsRef<str>>(
+        &self,
+        action: A,
+        target: T,
+        reason: &Option<String>,
+    ) -> TheiaAuditEntry {
+        let mut entry =
+            TheiaAuditEntry::new(self.plugin.instance_id(), action, target, self.origin);
+        entry.reason = reason.clone();
+        entry
+    }
+
+    /// Record an action taken by the plugin in the audit log.
+    async fn audit(&self, ctx: &SerenityContext, entry: TheiaAuditEntry) {
+        let data = ctx.data.read().await;
+        if let Some(theia) = data.get::<TheiaContainer>() {
+            theia.audit.record(&entry).await;
+        }
+    }
+
+    fn parse_emoji(&self, emoji: &str) -> Result<ReactionType, TheiaError> {
+        ReactionType::try_from(emoji)
+            .map_err(|_| self.error(TheiaPluginMessageError::InvalidEmoji(String::from(emoji))))
+    }
+
+    /// Find the message referenced by either a local handle, or a channel ID
+    /// and message ID.
+    ///
+    /// Messages referenced by ID must be in a channel the plugin may act in.
+    fn resolve_message(
+        &self,
+        handle: &Option<String>,
+        channel_id: &Option<String>,
+        message_id: &Option<String>,
+    ) -> Result<(ChannelId, MessageId), TheiaError> {
+        match (handle, channel_id, message_id) {
+            (Some(handle), _, _) => {
+                self.handles.get(handle).copied().ok_or_else(|| {
+                    self.error(TheiaPluginMessageError::UnknownHandle(handle.clone()))
+                })
+            }
+
+            (None, Some(channel_id), Some(message_id)) => {
+                let channel_id = ChannelId::from(self.id(channel_id)?);
+                self.require_channel(channel_id)?;
+
+                Ok((channel_id, MessageId::from(self.id(message_id)?)))
+            }
+
+            _ => Err(self.error(TheiaPluginMessageError::NoMessageReference)),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum TheiaPluginIncomingMessage {
     SendMessage {
         channel_id: String,
         in_reply_to: Option<String>,
+
+        #[serde(default)]
         content: String,
+
+        /// Rich embeds, validated against Discord's limits before sending
+        #[serde(default)]
+        embeds: Vec<TheiaPluginEmbed>,
+
+        /// Files to upload with the message
+        #[serde(default)]
+        attachments: Vec<TheiaPluginAttachment>,
+
+        /// Which mentions in the content notify anyone. Only the author of
+        /// the message being replied to is notified by default.
+        #[serde(default)]
+        allowed_mentions: Option<TheiaAllowedMentions>,
+
+        /// Local handle for the sent message, which later messages from the
+        /// same run can use to refer to it
+        #[serde(default)]
+        handle: Option<String>,
+
+        /// Plugin-chosen nonce, echoed back in the acknowledgement sent to
+        /// interactive plugins
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Send a direct message to a user, creating the DM channel if needed.
+    ///
+    /// If the user does not accept direct messages from the bot, the message
+    /// is skipped and the rest of the plugin's messages are still processed.
+    /// Interactive plugins are told with an `ActionFailed` acknowledgement,
+    /// if the message carried a nonce.
+    SendDirectMessage {
+        user_id: String,
+
+        #[serde(default)]
+        content: String,
+
+        #[serde(default)]
+        embeds: Vec<TheiaPluginEmbed>,
+
+        #[serde(default)]
+        attachments: Vec<TheiaPluginAttachment>,
+
+        #[serde(default)]
+        allowed_mentions: Option<TheiaAllowedMentions>,
+
+        #[serde(default)]
+        handle: Option<String>,
+
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Edit a message sent by this plugin, referenced either by a local handle
+    /// or by channel ID and message ID.
+    ///
+    /// Content and embeds are left unchanged if not given. Unlike sent
+    /// messages, edited content is not split, and must fit in one message.
+    EditMessage {
+        #[serde(default)]
+        handle: Option<String>,
+
+        #[serde(default)]
+        channel_id: Option<String>,
+
+        #[serde(default)]
+        message_id: Option<String>,
+
+        #[serde(default)]
+        content: Option<String>,
+
+        #[serde(default)]
+        embeds: Option<Vec<TheiaPluginEmbed>>,
+
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Delete a message sent by this plugin, referenced either by a local handle
+    /// or by channel ID and message ID.
+    DeleteMessage {
+        #[serde(default)]
+        handle: Option<String>,
+
+        #[serde(default)]
+        channel_id: Option<String>,
+
+        #[serde(default)]
+        message_id: Option<String>,
+
+        /// Plugin-chosen nonce, echoed back in the acknowledgement sent to
+        /// interactive plugins
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// React to a message, referenced either by a local handle or by channel
+    /// ID and message ID.
+    ///
+    /// `emoji` is either a Unicode emoji, or a custom emoji as `<:name:id>`.
+    AddReaction {
+        #[serde(default)]
+        handle: Option<String>,
+
+        #[serde(default)]
+        channel_id: Option<String>,
+
+        #[serde(default)]
+        message_id: Option<String>,
+
+        emoji: String,
+
+        /// Plugin-chosen nonce, echoed back in the acknowledgement sent to
+        /// interactive plugins
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Remove the bot's own reaction from a message, referenced as for
+    /// `AddReaction`.
+    RemoveReaction {
+        #[serde(default)]
+        handle: Option<String>,
+
+        #[serde(default)]
+        channel_id: Option<String>,
+
+        #[serde(default)]
+        message_id: Option<String>,
+
+        emoji: String,
+
+        /// Plugin-chosen nonce, echoed back in the acknowledgement sent to
+        /// interactive plugins
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Kick a member from a guild. Requires the `kick` capability.
+    ///
+    /// For this and the other moderation actions, the guild defaults to the
+    /// guild the plugin was run for. Any other guild requires the
+    /// `cross-guild` capability.
+    Kick {
+        #[serde(default)]
+        guild_id: Option<String>,
+
+        user_id: String,
+
+        #[serde(default)]
+        reason: Option<String>,
+
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Ban a user from a guild, optionally deleting up to 7 days of their
+    /// messages. Requires the `ban` capability.
+    Ban {
+        #[serde(default)]
+        guild_id: Option<String>,
+
+        user_id: String,
+
+        #[serde(default)]
+        delete_message_days: u8,
+
+        #[serde(default)]
+        reason: Option<String>,
+
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Time out a member of a guild for a number of seconds, or remove
+    /// their timeout if the duration is 0. Requires the `timeout`
+    /// capability.
+    Timeout {
+        #[serde(default)]
+        guild_id: Option<String>,
+
+        user_id: String,
+        duration: u64,
+
+        #[serde(default)]
+        reason: Option<String>,
+
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Add a role to a member of a guild. Requires the `manage-roles`
+    /// capability.
+    AddRole {
+        #[serde(default)]
+        guild_id: Option<String>,
+
+        user_id: String,
+        role_id: String,
+
+        #[serde(default)]
+        reason: Option<String>,
+
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Remove a role from a member of a guild. Requires the `manage-roles`
+    /// capability.
+    RemoveRole {
+        #[serde(default)]
+        guild_id: Option<String>,
+
+        user_id: String,
+        role_id: String,
+
+        #[serde(default)]
+        reason: Option<String>,
+
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Delete up to 100 messages in a channel at once. Requires the
+    /// `bulk-delete` capability, and the `send-anywhere` capability for any
+    /// channel other than the one the plugin was run for, as well as the
+    /// `cross-guild` capability for a channel in any other guild.
+    BulkDelete {
+        channel_id: String,
+        message_ids: Vec<String>,
+
+        #[serde(default)]
+        reason: Option<String>,
+
+        #[serde(default)]
+        nonce: Option<String>,
     },
+
+    /// The command was invoked with bad arguments.
+    ///
+    /// Theia replies to the invoking message with the reason, followed by
+    /// the help for the invoked command.
+    UsageError { reason: String },
 }
 
 impl TheiaPluginIncomingMessage {
-    pub async fn process<'c>(&self, ctx: &'c SerenityContext) -> Result<(), TheiaError> {
+    /// The plugin-chosen nonce carried by this message, if any.
+    pub fn nonce(&self) -> Option<&str> {
+        match self {
+            Self::SendMessage { nonce, .. }
+            | Self::SendDirectMessage { nonce, .. }
+            | Self::EditMessage { nonce, .. }
+            | Self::DeleteMessage { nonce, .. }
+            | Self::AddReaction { nonce, .. }
+            | Self::RemoveReaction { nonce, .. }
+            | Self::Kick { nonce, .. }
+            | Self::Ban { nonce, .. }
+            | Self::Timeout { nonce, .. }
+            | Self::AddRole { nonce, .. }
+            | Self::RemoveRole { nonce, .. }
+            | Self::BulkDelete { nonce, .. } => nonce.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Process a message received from a plugin, as part of a plugin run.
+    ///
+    /// Returns the message sent or edited, if any.
+    pub async fn process(
+        &self,
+        ctx: &SerenityContext,
+        invocation: &mut TheiaPluginInvocation<'_>,
+    ) -> Result<Option<SerenityDiscordMessage>, TheiaError> {
         trace!("Processing incoming plugin message: {:?}", self);
         match self {
             Self::SendMessage {
                 channel_id,
                 in_reply_to,
                 content,
+                embeds,
+                attachments,
+                allowed_mentions,
+                handle,
                 ..
             } => {
-                let channel_id = ChannelId::from(u64::from_str(channel_id)?);
+                let channel_id = ChannelId::from(invocation.id(channel_id)?);
+                invocation.require_channel(channel_id)?;
+
                 let mut message_id: Option<MessageId> = None;
                 if let Some(mid) = in_reply_to {
-                    message_id = Some(MessageId::from(u64::from_str(mid)?));
+                    message_id = Some(MessageId::from(invocation.id(mid)?));
                 }
 
-                channel_id
-                    .send_message(&ctx.http, |m| {
-                        if let Some(mid) = message_id {
-                            m.reference_message((channel_id, mid));
+                let sent = invocation
+                    .send_message(
+                        ctx,
+                        channel_id,
+                        message_id,
+                        content,
+                        embeds,
+                        attachments,
+                        allowed_mentions,
+                        handle,
+                    )
+                    .await?;
+
+                Ok(Some(sent))
+            }
+
+            Self::SendDirectMessage {
+                user_id,
+                content,
+                embeds,
+                attachments,
+                allowed_mentions,
+                handle,
+                ..
+            } => {
+                invocation.require(TheiaPluginCapability::DirectMessage)?;
+
+                // Report users not accepting DMs with a dedicated error
+                let instance_id = String::from(invocation.plugin.instance_id());
+                let dm_disabled = |why: TheiaError| match why.discord_error_code() {
+                    Some(DISCORD_CANNOT_MESSAGE_USER) => TheiaError::PluginMessage(
+                        instance_id.clone(),
+                        TheiaPluginMessageError::DirectMessagesDisabled(user_id.clone()),
+                    ),
+                    _ => why,
+                };
+
+                let channel = UserId::from(invocation.id(user_id)?)
+                    .create_dm_channel(ctx)
+                    .await
+                    .map_err(|why| dm_disabled(why.into()))?;
+
+                let sent = invocation
+                    .send_message(
+                        ctx,
+                        channel.id,
+                        None,
+                        content,
+                        embeds,
+                        attachments,
+                        allowed_mentions,
+                        handle,
+                    )
+                    .await
+                    .map_err(dm_disabled)?;
+
+                Ok(Some(sent))
+            }
+
+            Self::EditMessage {
+                handle,
+                channel_id,
+                message_id,
+                content,
+                embeds,
+                ..
+            } => {
+                if let Some(embeds) = embeds {
+                    if !embeds.is_empty() {
+                        invocation.require(TheiaPluginCapability::Embeds)?;
+                    }
+
+                    TheiaPluginEmbed::validate_all(embeds).map_err(|e| invocation.error(e))?;
+                }
+
+                // Edited messages can't be split
+                if let Some(content) = content {
+                    let length = content.chars().count();
+                    if length > MAX_MESSAGE_LENGTH {
+                        return Err(invocation.error(TheiaPluginMessageError::ContentTooLong {
+                            length,
+                            limit: MAX_MESSAGE_LENGTH,
+                        }));
+                    }
+                }
+
+                let (channel_id, message_id) =
+                    invocation.resolve_message(handle, channel_id, message_id)?;
+                if handle.is_none() {
+                    invocation.require_own_message(ctx, message_id).await?;
+                }
+
+                let edited = channel_id
+                    .edit_message(&ctx.http, message_id, |m| {
+                        if let Some(content) = content {
+                            m.content(content);
+                        }
+
+                        if let Some(embeds) = embeds {
+                            m.set_embeds(embeds.iter().map(CreateEmbed::from).collect());
                         }
 
-                        m.content(content);
                         m
                     })
                     .await?;
 
-                Ok(())
+                Ok(Some(edited))
+            }
+
+            Self::DeleteMessage {
+                handle,
+                channel_id,
+                message_id,
+                ..
+            } => {
+                let (channel_id, message_id) =
+                    invocation.resolve_message(handle, channel_id, message_id)?;
+
+                if handle.is_none() {
+                    invocation.require_own_message(ctx, message_id).await?;
+                }
+
+                channel_id.delete_message(&ctx.http, message_id).await?;
+                invocation.handles.retain(|_, (_, mid)| *mid != message_id);
+
+                Ok(None)
+            }
+
+            Self::AddReaction {
+                handle,
+                channel_id,
+                message_id,
+                emoji,
+                ..
+            } => {
+                let (channel_id, message_id) =
+                    invocation.resolve_message(handle, channel_id, message_id)?;
+                let emoji = invocation.parse_emoji(emoji)?;
+
+                channel_id
+                    .create_reaction(&ctx.http, message_id, emoji)
+                    .await?;
+
+                Ok(None)
+            }
+
+            Self::RemoveReaction {
+                handle,
+                channel_id,
+                message_id,
+                emoji,
+                ..
+            } => {
+                let (channel_id, message_id) =
+                    invocation.resolve_message(handle, channel_id, message_id)?;
+                let emoji = invocation.parse_emoji(emoji)?;
+
+                channel_id
+                    .delete_reaction(&ctx.http, message_id, None, emoji)
+                    .await?;
+
+                Ok(None)
+            }
+
+            Self::Kick {
+                guild_id,
+                user_id,
+                reason,
+                ..
+            } => {
+                invocation.require(TheiaPluginCapability::Kick)?;
+                let guild_id = invocation.guild_id(guild_id)?;
+                let user_id = UserId::from(invocation.id(user_id)?);
+
+                guild_id
+                    .kick_with_reason(&ctx.http, user_id, reason.as_deref().unwrap_or_default())
+                    .await?;
+
+                let mut entry = invocation.audit_entry("kick", user_id.to_string(), reason);
+                entry.guild_id = Some(guild_id.to_string());
+                invocation.audit(ctx, entry).await;
+                Ok(None)
+            }
+
+            Self::Ban {
+                guild_id,
+                user_id,
+                delete_message_days,
+                reason,
+                ..
+            } => {
+                invocation.require(TheiaPluginCapability::Ban)?;
+                if *delete_message_days > MAX_BAN_DELETE_DAYS {
+                    return Err(
+                        invocation.error(TheiaPluginMessageError::BanDeleteDaysTooMany {
+                            days: *delete_message_days,
+                            limit: MAX_BAN_DELETE_DAYS,
+                        }),
+                    );
+                }
+
+                let guild_id = invocation.guild_id(guild_id)?;
+                let user_id = UserId::from(invocation.id(user_id)?);
+
+                guild_id
+                    .ban_with_reason(
+                        &ctx.http,
+                        user_id,
+                        *delete_message_days,
+                        reason.as_deref().unwrap_or_default(),
+                    )
+                    .await?;
+
+                let mut entry = invocation.audit_entry("ban", user_id.to_string(), reason);
+                entry.guild_id = Some(guild_id.to_string());
+                invocation.audit(ctx, entry).await;
+                Ok(None)
+            }
+
+            Self::Timeout {
+                guild_id,
+                user_id,
+                duration,
+                reason,
+                ..
+            } => {
+                invocation.require(TheiaPluginCapability::Timeout)?;
+                if *duration > MAX_TIMEOUT_DURATION {
+                    return Err(invocation.error(TheiaPluginMessageError::TimeoutTooLong {
+                        duration: *duration,
+                        limit: MAX_TIMEOUT_DURATION,
+                    }));
+                }
+
+                let guild_id = invocation.guild_id(guild_id)?;
+                let user_id = UserId::from(invocation.id(user_id)?);
+
+                guild_id
+                    .edit_member(&ctx.http, user_id, |m| {
+                        if *duration == 0 {
+                            m.enable_communication()
+                        } else {
+                            m.disable_communication_until_datetime(
+                                ::chrono::Utc::now()
+                                    + ::chrono::Duration::seconds(*duration as i64),
+                            )
+                        }
+                    })
+                    .await?;
+
+                let (action, target) = if *duration == 0 {
+                    ("remove-timeout", user_id.to_string())
+                } else {
+                    ("timeout", format!("{} for {}s", user_id, duration))
+                };
+
+                let mut entry = invocation.audit_entry(action, target, reason);
+                entry.guild_id = Some(guild_id.to_string());
+                invocation.audit(ctx, entry).await;
+                Ok(None)
+            }
+
+            Self::AddRole {
+                guild_id,
+                user_id,
+                role_id,
+                reason,
+                ..
+            }
+            | Self::RemoveRole {
+                guild_id,
+                user_id,
+                role_id,
+                reason,
+                ..
+            } => {
+                invocation.require(TheiaPluginCapability::ManageRoles)?;
+                let guild_id = invocation.guild_id(guild_id)?;
+                let user_id = invocation.id(user_id)?;
+                let role_id = invocation.id(role_id)?;
+
+                let action = if let Self::AddRole { .. } = self {
+                    ctx.http
+                        .add_member_role(guild_id.0, user_id, role_id)
+                        .await?;
+                    "add-role"
+                } else {
+                    ctx.http
+                        .remove_member_role(guild_id.0, user_id, role_id)
+                        .await?;
+                    "remove-role"
+                };
+
+                let target = format!("user {} role {}", user_id, role_id);
+                let mut entry = invocation.audit_entry(action, target, reason);
+                entry.guild_id = Some(guild_id.to_string());
+                invocation.audit(ctx, entry).await;
+                Ok(None)
+            }
+
+            Self::BulkDelete {
+                channel_id,
+                message_ids,
+                reason,
+                ..
+            } => {
+                invocation.require(TheiaPluginCapability::BulkDelete)?;
+                if message_ids.is_empty() || message_ids.len() > MAX_BULK_DELETE {
+                    return Err(invocation.error(TheiaPluginMessageError::BulkDeleteCount {
+                        count: message_ids.len(),
+                        limit: MAX_BULK_DELETE,
+                    }));
+                }
+
+                let channel_id = ChannelId::from(invocation.id(channel_id)?);
+                invocation.require_channel(channel_id)?;
+                invocation.require_channel_guild(ctx, channel_id).await?;
+
+                let mut ids = Vec::new();
+                for message_id in message_ids.iter() {
+                    ids.push(MessageId::from(invocation.id(message_id)?));
+                }
+
+                if ids.len() == 1 {
+                    channel_id.delete_message(&ctx.http, ids[0]).await?;
+                } else {
+                    channel_id.delete_messages(&ctx.http, &ids).await?;
+                }
+
+                let mut entry =
+                    invocation.audit_entry("bulk-delete", message_ids.join(","), reason);
+                entry.channel_id = Some(channel_id.to_string());
+                invocation.audit(ctx, entry).await;
+                Ok(None)
+            }
+
+            Self::UsageError { reason } => {
+                let (origin, cmd) = match invocation
+                    .origin
+                    .and_then(|m| m.command_invocation.as_ref().map(|cmd| (m, cmd)))
+                {
+                    Some(origin) => origin,
+                    None => {
+                        warn!(
+                            "Plugin {:?} sent a usage error outside of a command invocation: {}",
+                            invocation.plugin.instance_id(),
+                            reason
+                        );
+
+                        return Ok(None);
+                    }
+                };
+
+                let data = ctx.data.read().await;
+                let theia = data.get::<TheiaContainer>().unwrap();
+
+                let plugin = invocation.plugin;
+                let mut reply = format!("\u{274c} {}", reason);
+                let chain = plugin.command_chain(&cmd.command, &cmd.subcommand);
+                if let Some(cmdcfg) = chain.last() {
+                    let help_s = render_command_help(
+                        theia.prefix(),
+                        theia.command_chain_display_name(plugin, &chain),
+                        cmdcfg,
+                    );
+
+                    if !help_s.trim().is_empty() {
+                        reply = format!("{}\n\n{}", reply, help_s);
+                    }
+                }
+
+                origin.reply(&ctx.http, reply).await?;
+                Ok(None)
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_message_references() {
+        let plugin = TheiaPlugin {
+            instance_id: String::from("test"),
+            path: Default::default(),
+            config: ::toml::from_str("name = \"test\"\nrun = \"true\"\ncommand = []").unwrap(),
+            cfgdata: Default::default(),
+            grants: Vec::new(),
+        };
+
+        let mut invocation = TheiaPluginInvocation::new(&plugin, &[]);
+        invocation
+            .handles
+            .insert(String::from("status"), (ChannelId(1), MessageId(2)));
+
+        let some = |s: &str| Some(String::from(s));
+        assert_eq!(
+            (ChannelId(1), MessageId(2)),
+            invocation
+                .resolve_message(&some("status"), &None, &None)
+                .unwrap()
+        );
+        assert!(matches!(
+            invocation.resolve_message(&None, &some("3"), &some("4")),
+            Err(TheiaError::PluginMessage(
+                _,
+                TheiaPluginMessageError::CapabilityNotDeclared("send-anywhere")
+            ))
+        ));
+
+        invocation.channel_id = Some(ChannelId(3));
+        assert_eq!(
+            (ChannelId(3), MessageId(4)),
+            invocation
+                .resolve_message(&None, &some("3"), &some("4"))
+                .unwrap()
+        );
+        assert!(matches!(
+            invocation.resolve_message(&some("other"), &None, &None),
+            Err(TheiaError::PluginMessage(
+                _,
+                TheiaPluginMessageError::UnknownHandle(_)
+            ))
+        ));
+        assert!(matches!(
+            invocation.resolve_message(&None, &some("3"), &None),
+            Err(TheiaError::PluginMessage(
+                _,
+                TheiaPluginMessageError::NoMessageReference
+            ))
+        ));
+    }
+
+    #[test]
+    fn moderation_confined_to_guild() {
+        let mut plugin = TheiaPlugin {
+            instance_id: String::from("test"),
+            path: Default::default(),
+            config: ::toml::from_str(
+                "name = \"test\"\nrun = \"true\"\ncommand = []\ncapabilities = [\"cross-guild\"]",
+            )
+            .unwrap(),
+            cfgdata: Default::default(),
+            grants: Vec::new(),
+        };
+
+        let mut invocation = TheiaPluginInvocation::new(&plugin, &[]);
+        let some = |s: &str| Some(String::from(s));
+        assert!(matches!(
+            invocation.guild_id(&None),
+            Err(TheiaError::PluginMessage(
+                _,
+                TheiaPluginMessageError::NoGuild
+            ))
+        ));
+
+        invocation.guild_id = Some(GuildId(1));
+        assert_eq!(GuildId(1), invocation.guild_id(&None).unwrap());
+        assert_eq!(GuildId(1), invocation.guild_id(&some("1")).unwrap());
+        assert!(matches!(
+            invocation.guild_id(&some("2")),
+            Err(TheiaError::PluginMessage(
+                _,
+                TheiaPluginMessageError::CapabilityNotGranted("cross-guild")
+            ))
+        ));
+
+        plugin.grants.push(TheiaPluginCapability::CrossGuild);
+        let mut invocation = TheiaPluginInvocation::new(&plugin, &[]);
+        invocation.guild_id = Some(GuildId(1));
+        assert_eq!(GuildId(2), invocation.guild_id(&some("2")).unwrap());
+    }
+
+    #[test]
+    fn nonces() {
+        let msg: TheiaPluginIncomingMessage = ::serde_json::from_str(
+            r#"{"SendMessage": {"channel_id": "1", "in_reply_to": null, "content": "hi", "nonce": "abc"}}"#,
+        )
+        .unwrap();
+        assert_eq!(Some("abc"), msg.nonce());
+
+        let msg: TheiaPluginIncomingMessage =
+            ::serde_json::from_str(r#"{"UsageError": {"reason": "bad"}}"#).unwrap();
+        assert_eq!(None, msg.nonce());
+
+        let ack = TheiaPluginOutgoingMessage::ActionFailed {
+            nonce: String::from("abc"),
+            error: String::from("oops"),
+        };
+        assert_eq!(
+            r#"{"ActionFailed":{"nonce":"abc","error":"oops"}}"#,
+            ::serde_json::to_string(&ack).unwrap()
+        );
+        assert_eq!(
+            r#"{"ActionDone":{"nonce":"abc"}}"#,
+            ::serde_json::to_string(&TheiaPluginOutgoingMessage::ActionDone {
+                nonce: String::from("abc")
+            })
+            .unwrap()
+        );
+        assert_eq!(
+            r#"{"EndOfInput":{}}"#,
+            ::serde_json::to_string(&TheiaPluginOutgoingMessage::EndOfInput {}).unwrap()
+        );
+    }
+
+    #[test]
+    fn capabilities_declared_and_granted() {
+        let mut plugin = TheiaPlugin {
+            instance_id: String::from("mod"),
+            path: Default::default(),
+            config: ::toml::from_str(
+                "name = \"mod\"\nrun = \"true\"\ncapabilities = [\"kick\", \"bulk-delete\"]\ncommand = []",
+            )
+            .unwrap(),
+            cfgdata: Default::default(),
+            grants: vec![TheiaPluginCapability::Kick, TheiaPluginCapability::Ban],
+        };
+
+        assert_eq!(Ok(()), plugin.check_capability(TheiaPluginCapability::Kick));
+        assert_eq!(
+            Err(TheiaPluginMessageError::CapabilityNotDeclared("ban")),
+            plugin.check_capability(TheiaPluginCapability::Ban)
+        );
+        assert_eq!(
+            Err(TheiaPluginMessageError::CapabilityNotGranted("bulk-delete")),
+            plugin.check_capability(TheiaPluginCapability::BulkDelete)
+        );
+
+        plugin.grants.clear();
+        let invocation = TheiaPluginInvocation::new(&plugin, &[]);
+        assert!(matches!(
+            invocation.require(TheiaPluginCapability::Kick),
+            Err(TheiaError::PluginMessage(
+                _,
+                TheiaPluginMessageError::CapabilityNotGranted("kick")
+            ))
+        ));
+        assert!(matches!(
+            invocation.guild_id(&None),
+            Err(TheiaError::PluginMessage(
+                _,
+                TheiaPluginMessageError::NoGuild
+            ))
+        ));
+    }
+}
@@ -1,5 +1,8 @@
+use crate::config::TheiaCommandOverride;
+use crate::error::TheiaPluginLoadError;
+use crate::util::normalize::normalize_name;
 use crate::util::runnable::RunnableCommand;
-use ::serde::Deserialize;
+use ::serde::{Deserialize, Serialize};
 use ::serde_json::Value;
 use ::std::collections::HashMap;
 
@@ -25,14 +28,167 @@ pub struct TheiaPluginConfig {
     #[serde(default, rename = "config")]
     pub cfgdata: HashMap<String, Value>,
 
+    /// Maximum number of seconds a single run of the plugin may take before
+    /// it is killed. Runs are not limited if unset, unless the plugin is
+    /// interactive, in which case they are limited to
+    /// [`DEFAULT_INTERACTIVE_TIMEOUT`] seconds.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+
+    /// Whether to keep the plugin's stdin open while it runs.
+    ///
+    /// Messages from the plugin are then processed as soon as they are
+    /// received, and any carrying a `nonce` are acknowledged over stdin with
+    /// a `MessageSent` message for sent or edited messages, an `ActionDone`
+    /// message for other successful requests, or an `ActionFailed` message.
+    /// The initial messages are followed by an `EndOfInput` message, as
+    /// stdin isn't closed, so interactive plugins must not wait for stdin to
+    /// be closed before responding.
+    #[serde(default)]
+    pub interactive: bool,
+
+    /// Events to send to the plugin, in addition to command invocations.
+    ///
+    /// Reaction events are only sent for messages the plugin sent.
+    #[serde(default)]
+    pub subscribe: Vec<TheiaPluginEventKind>,
+
+    /// Capabilities the plugin needs. These must also be granted to the
+    /// plugin in the bot configuration before the plugin can use them.
+    #[serde(default)]
+    pub capabilities: Vec<TheiaPluginCapability>,
+
     /// List of commands known by this plugin.
     #[serde(rename = "command")]
     pub commands: Vec<TheiaPluginCommandConfig>,
 }
 
+/// Kinds of events a plugin can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TheiaPluginEventKind {
+    /// A reaction was added to a message.
+    ReactionAdd,
+
+    /// A reaction was removed from a message.
+    ReactionRemove,
+}
+
+/// Capabilities a plugin can declare, and an operator can grant.
+///
+/// Without any capabilities, a plugin can only send plain messages to, and
+/// act on messages in, the channel it was invoked in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TheiaPluginCapability {
+    /// Send messages to, and act on messages in, any channel, rather than
+    /// only the channel the plugin was invoked in.
+    SendAnywhere,
+
+    /// Send direct messages to users.
+    DirectMessage,
+
+    /// Send messages with embeds.
+    Embeds,
+
+    /// Choose which mentions in sent messages notify anyone, with
+    /// `allowed_mentions`.
+    Mentions,
+
+    /// Mention `@everyone` and `@here`.
+    MentionEveryone,
+
+    /// Kick members from a guild.
+    Kick,
+
+    /// Ban users from a guild.
+    Ban,
+
+    /// Time out members of a guild.
+    Timeout,
+
+    /// Add roles to and remove roles from members of a guild.
+    ManageRoles,
+
+    /// Delete multiple messages at once, including those of other users.
+    BulkDelete,
+
+    /// Take moderation actions in guilds other than the one the plugin was
+    /// run for.
+    CrossGuild,
+}
+
+impl TheiaPluginCapability {
+    /// The name of the capability, as written in configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SendAnywhere => "send-anywhere",
+            Self::DirectMessage => "direct-message",
+            Self::Embeds => "embeds",
+            Self::Mentions => "mentions",
+            Self::MentionEveryone => "mention-everyone",
+            Self::Kick => "kick",
+            Self::Ban => "ban",
+            Self::Timeout => "timeout",
+            Self::ManageRoles => "manage-roles",
+            Self::BulkDelete => "bulk-delete",
+            Self::CrossGuild => "cross-guild",
+        }
+    }
+}
+
+/// Timeout for runs of interactive plugins without their own `timeout`, in
+/// seconds.
+pub const DEFAULT_INTERACTIVE_TIMEOUT: u64 = 60;
+
 impl TheiaPluginConfig {
+    /// Maximum number of seconds a single run of the plugin may take, if
+    /// limited.
+    pub fn run_timeout(&self) -> Option<u64> {
+        match self.timeout {
+            Some(secs) => Some(secs),
+            None if self.interactive => Some(DEFAULT_INTERACTIVE_TIMEOUT),
+            None => None,
+        }
+    }
+
+    /// Check the argument schemas of this plugin's commands and
+    /// subcommands, which may only have a variadic argument last.
+    pub fn validate(&self) -> Result<(), TheiaPluginLoadError> {
+        fn validate_recursive(
+            cmdcfg: &TheiaPluginCommandConfig,
+        ) -> Result<(), TheiaPluginLoadError> {
+            if let Some(args) = &cmdcfg.args {
+                let count = args.len();
+                if let Some(argdef) = args
+                    .iter()
+                    .take(count.saturating_sub(1))
+                    .find(|a| a.variadic)
+                {
+                    return Err(TheiaPluginLoadError::NonFinalVariadic {
+                        command: cmdcfg.name.clone(),
+                        argument: argdef.name.clone(),
+                    });
+                }
+            }
+
+            cmdcfg.subcommands.iter().try_for_each(validate_recursive)
+        }
+
+        self.commands.iter().try_for_each(validate_recursive)
+    }
+
+    /// Checks whether the plugin subscribed to an event kind.
+    pub fn subscribes_to(&self, kind: TheiaPluginEventKind) -> bool {
+        self.subscribe.contains(&kind)
+    }
+
     pub fn handle_help<T: AsRef<str>>(&self, cmd_name: T) -> bool {
-        if let Some(command) = self.commands.iter().find(|e| e.name == cmd_name.as_ref()) {
+        if let Some(command) = self
+            .commands
+            .iter()
+            .find(|e| e.invoke_name() == cmd_name.as_ref())
+        {
             if let Some(passthru) = command.help_passthrough {
                 return !passthru;
             }
@@ -40,6 +196,96 @@ impl TheiaPluginConfig {
 
         !self.help_passthrough
     }
+
+    /// Set whether this plugin's commands and subcommands are matched
+    /// case-insensitively.
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        fn set_recursive(cmdcfg: &mut TheiaPluginCommandConfig, case_insensitive: bool) {
+            cmdcfg.case_insensitive = case_insensitive;
+            for subcmd in cmdcfg.subcommands.iter_mut() {
+                set_recursive(subcmd, case_insensitive);
+            }
+        }
+
+        for cmdcfg in self.commands.iter_mut() {
+            set_recursive(cmdcfg, case_insensitive);
+        }
+    }
+
+    /// Add a prefix and/or suffix to the names and aliases of all of this
+    /// plugin's commands.
+    pub fn apply_command_affixes(&mut self, prefix: Option<&str>, suffix: Option<&str>) {
+        if prefix.is_none() && suffix.is_none() {
+            return;
+        }
+
+        let affix = |name: &str| {
+            format!(
+                "{}{}{}",
+                prefix.unwrap_or_default(),
+                name,
+                suffix.unwrap_or_default()
+            )
+        };
+
+        for cmdcfg in self.commands.iter_mut() {
+            cmdcfg.plugin_name = Some(String::from(cmdcfg.invoke_name()));
+            cmdcfg.name = affix(&cmdcfg.name);
+            cmdcfg.aliases = cmdcfg.aliases.iter().map(|a| affix(a)).collect();
+        }
+    }
+
+    /// Merge the bot configuration's overrides over this plugin's commands.
+    ///
+    /// Overrides are keyed by the command name as defined by the plugin.
+    pub fn apply_command_overrides(&mut self, overrides: &HashMap<String, TheiaCommandOverride>) {
+        for (cmd_name, cmd_override) in overrides.iter() {
+            let cmdcfg = match self
+                .commands
+                .iter_mut()
+                .find(|c| c.invoke_name() == cmd_name)
+            {
+                Some(cmdcfg) => cmdcfg,
+                None => {
+                    ::tracing::warn!(
+                        "Override for unknown command {0:?} of plugin {1:?}",
+                        cmd_name,
+                        self.name,
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(name) = &cmd_override.name {
+                cmdcfg.plugin_name = Some(String::from(cmdcfg.invoke_name()));
+                cmdcfg.name = name.clone();
+            }
+
+            cmdcfg
+                .aliases
+                .retain(|a| !cmd_override.remove_aliases.contains(a));
+            for alias in cmd_override.add_aliases.iter() {
+                if !cmdcfg.aliases.contains(alias) {
+                    cmdcfg.aliases.push(alias.clone());
+                }
+            }
+
+            if let Some(hidden) = cmd_override.hidden {
+                cmdcfg.hidden = hidden;
+            }
+
+            if let Some(help) = &cmd_override.help {
+                cmdcfg.help = help.clone();
+            }
+        }
+
+        self.commands.retain(|c| {
+            !overrides
+                .get(c.invoke_name())
+                .map(|o| o.disabled)
+                .unwrap_or(false)
+        });
+    }
 }
 
 /// Configuration for an individual plugin-based command.
@@ -63,4 +309,259 @@ pub struct TheiaPluginCommandConfig {
     /// Whether this command is hidden in global command listings.
     #[serde(default)]
     pub hidden: bool,
+
+    /// Whether this command can only be used by the bot's admin users.
+    ///
+    /// For subcommands, this applies in addition to any parent command's
+    /// setting.
+    #[serde(default, rename = "admin-only")]
+    pub admin_only: bool,
+
+    /// Options for this command which take a value from the following
+    /// argument, such as `user` for `--user foo`.
+    #[serde(default, rename = "value-options")]
+    pub value_options: Vec<String>,
+
+    /// Argument schema for this command.
+    ///
+    /// If present, arguments are validated and converted before the command
+    /// is invoked, and the results are sent to the plugin in `parsed_args`.
+    #[serde(default)]
+    pub args: Option<Vec<TheiaPluginCommandArgument>>,
+
+    /// Subcommands of this command.
+    #[serde(default, rename = "subcommand")]
+    pub subcommands: Vec<TheiaPluginCommandConfig>,
+
+    /// Name of this command as defined by the plugin, if the bot
+    /// configuration has renamed it.
+    #[serde(skip)]
+    pub plugin_name: Option<String>,
+
+    /// Whether this command's name and aliases are matched
+    /// case-insensitively, as set by the bot configuration.
+    #[serde(skip)]
+    pub case_insensitive: bool,
+}
+
+impl TheiaPluginCommandConfig {
+    /// Name of this command as sent to the plugin when it is invoked.
+    pub fn invoke_name(&self) -> &str {
+        self.plugin_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Checks whether the given name is this command's name or an alias.
+    pub fn matches<T: AsRef<str>>(&self, name: T) -> bool {
+        if self.case_insensitive {
+            let name = normalize_name(name);
+            return ::std::iter::once(&self.name)
+                .chain(self.aliases.iter())
+                .any(|n| normalize_name(n) == name);
+        }
+
+        self.name == name.as_ref() || self.aliases.iter().any(|a| a == name.as_ref())
+    }
+
+    /// Find the subcommand with the given name or alias.
+    pub fn subcommand<T: AsRef<str>>(&self, name: T) -> Option<&TheiaPluginCommandConfig> {
+        self.subcommands.iter().find(|s| s.matches(&name))
+    }
+
+    /// Resolve as many leading `args` as possible to nested subcommands.
+    ///
+    /// Returns the chain of subcommands matched, outermost first.
+    pub fn resolve_subcommands<T: AsRef<str>>(&self, args: &[T]) -> Vec<&TheiaPluginCommandConfig> {
+        let mut chain: Vec<&TheiaPluginCommandConfig> = Vec::new();
+        let mut current = self;
+        for arg in args.iter() {
+            match current.subcommand(arg) {
+                Some(subcmd) => {
+                    chain.push(subcmd);
+                    current = subcmd;
+                }
+
+                None => break,
+            }
+        }
+
+        chain
+    }
+}
+
+/// Schema for an individual command argument.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TheiaPluginCommandArgument {
+    /// Name of this argument.
+    pub name: String,
+
+    /// Type of this argument.
+    #[serde(default, rename = "type")]
+    pub kind: TheiaPluginCommandArgumentType,
+
+    /// Whether this argument may be omitted.
+    #[serde(default)]
+    pub optional: bool,
+
+    /// Whether this argument consumes all remaining arguments.
+    ///
+    /// Only valid on the last argument in a schema.
+    #[serde(default)]
+    pub variadic: bool,
+}
+
+/// Type of a command argument.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TheiaPluginCommandArgumentType {
+    /// Any string, passed through unchanged.
+    #[default]
+    String,
+
+    /// A signed integer.
+    Integer,
+
+    /// A user mention or ID, converted to the user ID.
+    User,
+
+    /// A channel mention or ID, converted to the channel ID.
+    Channel,
+
+    /// A role mention or ID, converted to the role ID.
+    Role,
+
+    /// A duration such as `1h30m`, converted to a number of seconds.
+    Duration,
+}
+
+impl TheiaPluginCommandArgumentType {
+    /// Human-readable description of this type, for usage errors.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::String => "a string",
+            Self::Integer => "an integer",
+            Self::User => "a user mention or ID",
+            Self::Channel => "a channel mention or ID",
+            Self::Role => "a role mention or ID",
+            Self::Duration => "a duration (e.g. `1h30m`)",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interactive_timeout_default() {
+        let config = |extra: &str| -> TheiaPluginConfig {
+            ::toml::from_str(&format!(
+                "name = \"test\"\nrun = \"true\"\ncommand = []\n{}",
+                extra
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(None, config("").run_timeout());
+        assert_eq!(Some(5), config("timeout = 5").run_timeout());
+        assert_eq!(
+            Some(DEFAULT_INTERACTIVE_TIMEOUT),
+            config("interactive = true").run_timeout()
+        );
+        assert_eq!(
+            Some(5),
+            config("interactive = true\ntimeout = 5").run_timeout()
+        );
+    }
+
+    #[test]
+    fn non_final_variadic_rejected() {
+        let config = |args: &str| -> TheiaPluginConfig {
+            ::toml::from_str(&format!(
+                r#"
+                    name = "test"
+                    run = "true"
+
+                    [[command]]
+                    name = "parent"
+
+                    [[command.subcommand]]
+                    name = "child"
+                    args = {}
+                "#,
+                args
+            ))
+            .unwrap()
+        };
+
+        let valid = config(r#"[{ name = "a" }, { name = "b", variadic = true }]"#);
+        assert_eq!(Ok(()), valid.validate());
+
+        let invalid = config(r#"[{ name = "a", variadic = true }, { name = "b" }]"#);
+        assert_eq!(
+            Err(TheiaPluginLoadError::NonFinalVariadic {
+                command: String::from("child"),
+                argument: String::from("a"),
+            }),
+            invalid.validate()
+        );
+    }
+
+    #[test]
+    fn command_overrides() {
+        let mut config: TheiaPluginConfig = ::toml::from_str(
+            r#"
+                name = "test"
+                run = "true"
+
+                [[command]]
+                name = "info"
+                aliases = ["about", "i"]
+                help = "`%!` - info"
+
+                [[command]]
+                name = "debug"
+            "#,
+        )
+        .unwrap();
+
+        let overrides: HashMap<String, TheiaCommandOverride> = ::toml::from_str(
+            r#"
+                [info]
+                name = "pinfo"
+                add_aliases = ["pi"]
+                remove_aliases = ["i"]
+                hidden = true
+                help = "`%!` - plugin info"
+
+                [debug]
+                disabled = true
+            "#,
+        )
+        .unwrap();
+
+        config.apply_command_overrides(&overrides);
+        assert_eq!(1, config.commands.len());
+
+        let cmdcfg = &config.commands[0];
+        assert_eq!("pinfo", &cmdcfg.name);
+        assert_eq!("info", cmdcfg.invoke_name());
+        assert_eq!(
+            vec![String::from("about"), String::from("pi")],
+            cmdcfg.aliases
+        );
+        assert!(cmdcfg.hidden);
+        assert_eq!("`%!` - plugin info", &cmdcfg.help);
+        assert!(!cmdcfg.matches("PInfo"));
+
+        config.apply_command_affixes(Some("news-"), None);
+        assert_eq!("news-pinfo", &config.commands[0].name);
+        assert_eq!("info", config.commands[0].invoke_name());
+        assert_eq!(
+            vec![String::from("news-about"), String::from("news-pi")],
+            config.commands[0].aliases
+        );
+
+        config.set_case_insensitive(true);
+        assert!(config.commands[0].matches("NEWS-PInfo"));
+    }
 }
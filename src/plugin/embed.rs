@@ -0,0 +1,389 @@
+//! Rich embeds sent by plugins.
+
+use crate::error::TheiaPluginMessageError;
+use ::serde::{Deserialize, Serialize};
+use ::serenity::builder::CreateEmbed;
+
+/// Maximum number of embeds in a single message.
+pub const MAX_EMBEDS: usize = 10;
+
+/// Maximum number of fields in a single embed.
+pub const MAX_EMBED_FIELDS: usize = 25;
+
+/// Maximum total length of all text in all embeds of a single message.
+pub const MAX_EMBED_TOTAL_LENGTH: usize = 6000;
+
+const MAX_TITLE_LENGTH: usize = 256;
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+const MAX_FIELD_NAME_LENGTH: usize = 256;
+const MAX_FIELD_VALUE_LENGTH: usize = 1024;
+const MAX_FOOTER_LENGTH: usize = 2048;
+
+/// An embed in a message sent by a plugin.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TheiaPluginEmbed {
+    /// Embed title.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Embed description.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// URL the title links to.
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Colour of the embed's side bar, as an RGB integer.
+    #[serde(default, alias = "color")]
+    pub colour: Option<u32>,
+
+    /// Embed fields.
+    #[serde(default)]
+    pub fields: Vec<TheiaPluginEmbedField>,
+
+    /// Embed footer.
+    #[serde(default)]
+    pub footer: Option<TheiaPluginEmbedFooter>,
+
+    /// URL of the embed's thumbnail image.
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+
+    /// Timestamp shown in the embed's footer, in RFC 3339 format.
+    #[serde(default)]
+    pub timestamp: Option<String>,
+}
+
+/// A field in an embed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TheiaPluginEmbedField {
+    pub name: String,
+    pub value: String,
+
+    /// Whether the field is shown side-by-side with other inline fields.
+    #[serde(default)]
+    pub inline: bool,
+}
+
+/// The footer of an embed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TheiaPluginEmbedFooter {
+    pub text: String,
+
+    #[serde(default)]
+    pub icon_url: Option<String>,
+}
+
+impl TheiaPluginEmbed {
+    /// Total length of all text in this embed, as counted by Discord.
+    pub fn text_length(&self) -> usize {
+        let len = |s: &Option<String>| s.as_deref().map_or(0, |s| s.chars().count());
+
+        len(&self.title)
+            + len(&self.description)
+            + self.footer.as_ref().map_or(0, |f| f.text.chars().count())
+            + self
+                .fields
+                .iter()
+                .map(|f| f.name.chars().count() + f.value.chars().count())
+                .sum::<usize>()
+    }
+
+    /// Check this embed against Discord's limits.
+    ///
+    /// `index` is the position of this embed in its message, used to point
+    /// at the offending part of the embed in errors.
+    pub fn validate(&self, index: usize) -> Result<(), TheiaPluginMessageError> {
+        let check = |field: String, value: &str, limit: usize| {
+            let length = value.chars().count();
+            if length > limit {
+                Err(TheiaPluginMessageError::EmbedFieldTooLong {
+                    field,
+                    length,
+                    limit,
+                })
+            } else {
+                Ok(())
+            }
+        };
+
+        if let Some(title) = &self.title {
+            check(format!("embeds[{}].title", index), title, MAX_TITLE_LENGTH)?;
+        }
+
+        if let Some(description) = &self.description {
+            check(
+                format!("embeds[{}].description", index),
+                description,
+                MAX_DESCRIPTION_LENGTH,
+            )?;
+        }
+
+        if let Some(footer) = &self.footer {
+            check(
+                format!("embeds[{}].footer.text", index),
+                &footer.text,
+                MAX_FOOTER_LENGTH,
+            )?;
+        }
+
+        let url = |field: &str, value: &Option<String>, schemes: &[&str]| match value {
+            Some(value) if !is_url(value, schemes) => {
+                Err(TheiaPluginMessageError::InvalidEmbedField {
+                    field: format!("embeds[{}].{}", index, field),
+                    expected: if schemes.contains(&"attachment") {
+                        "an http(s) or attachment:// URL"
+                    } else {
+                        "an http(s) URL"
+                    },
+                })
+            }
+
+            _ => Ok(()),
+        };
+
+        url("url", &self.url, &["http", "https"])?;
+        url(
+            "thumbnail",
+            &self.thumbnail,
+            &["http", "https", "attachment"],
+        )?;
+        if let Some(footer) = &self.footer {
+            url(
+                "footer.icon_url",
+                &footer.icon_url,
+                &["http", "https", "attachment"],
+            )?;
+        }
+
+        if let Some(timestamp) = &self.timestamp {
+            if ::chrono::DateTime::parse_from_rfc3339(timestamp).is_err() {
+                return Err(TheiaPluginMessageError::InvalidEmbedField {
+                    field: format!("embeds[{}].timestamp", index),
+                    expected: "an RFC 3339 timestamp",
+                });
+            }
+        }
+
+        if self.fields.len() > MAX_EMBED_FIELDS {
+            return Err(TheiaPluginMessageError::TooManyEmbedFields {
+                embed: index,
+                count: self.fields.len(),
+                limit: MAX_EMBED_FIELDS,
+            });
+        }
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let name = format!("embeds[{}].fields[{}].name", index, i);
+            let value = format!("embeds[{}].fields[{}].value", index, i);
+            if field.name.trim().is_empty() {
+                return Err(TheiaPluginMessageError::EmptyEmbedField(name));
+            }
+
+            if field.value.trim().is_empty() {
+                return Err(TheiaPluginMessageError::EmptyEmbedField(value));
+            }
+
+            check(name, &field.name, MAX_FIELD_NAME_LENGTH)?;
+            check(value, &field.value, MAX_FIELD_VALUE_LENGTH)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check a list of embeds for a single message against Discord's limits.
+    pub fn validate_all(embeds: &[Self]) -> Result<(), TheiaPluginMessageError> {
+        if embeds.len() > MAX_EMBEDS {
+            return Err(TheiaPluginMessageError::TooManyEmbeds {
+                count: embeds.len(),
+                limit: MAX_EMBEDS,
+            });
+        }
+
+        for (index, embed) in embeds.iter().enumerate() {
+            embed.validate(index)?;
+        }
+
+        let length: usize = embeds.iter().map(Self::text_length).sum();
+        if length > MAX_EMBED_TOTAL_LENGTH {
+            return Err(TheiaPluginMessageError::EmbedsTooLong {
+                length,
+                limit: MAX_EMBED_TOTAL_LENGTH,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks whether a value is a URL with one of the given schemes and a
+/// non-empty remainder.
+fn is_url(value: &str, schemes: &[&str]) -> bool {
+    match value.split_once("://") {
+        Some((scheme, rest)) => {
+            schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme))
+                && !rest.is_empty()
+                && !rest.contains(char::is_whitespace)
+        }
+
+        None => false,
+    }
+}
+
+impl From<&TheiaPluginEmbed> for CreateEmbed {
+    fn from(embed: &TheiaPluginEmbed) -> CreateEmbed {
+        let mut e = CreateEmbed::default();
+        if let Some(title) = &embed.title {
+            e.title(title);
+        }
+
+        if let Some(description) = &embed.description {
+            e.description(description);
+        }
+
+        if let Some(url) = &embed.url {
+            e.url(url);
+        }
+
+        if let Some(colour) = embed.colour {
+            e.colour(colour);
+        }
+
+        for field in embed.fields.iter() {
+            e.field(&field.name, &field.value, field.inline);
+        }
+
+        if let Some(footer) = &embed.footer {
+            e.footer(|f| {
+                f.text(&footer.text);
+                if let Some(icon_url) = &footer.icon_url {
+                    f.icon_url(icon_url);
+                }
+
+                f
+            });
+        }
+
+        if let Some(thumbnail) = &embed.thumbnail {
+            e.thumbnail(thumbnail);
+        }
+
+        if let Some(timestamp) = &embed.timestamp {
+            e.timestamp(timestamp.clone());
+        }
+
+        e
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embed(json: &str) -> TheiaPluginEmbed {
+        ::serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn valid_embed() {
+        let e = embed(
+            r#"{
+                "title": "Hello",
+                "color": 16711680,
+                "fields": [{"name": "a", "value": "b", "inline": true}],
+                "footer": {"text": "footer"}
+            }"#,
+        );
+
+        assert_eq!(Some(0xff0000), e.colour);
+        assert_eq!(13, e.text_length());
+        assert_eq!(Ok(()), TheiaPluginEmbed::validate_all(&[e]));
+    }
+
+    #[test]
+    fn limits() {
+        let long_title = TheiaPluginEmbed {
+            title: Some("x".repeat(257)),
+            ..Default::default()
+        };
+        assert_eq!(
+            Err(TheiaPluginMessageError::EmbedFieldTooLong {
+                field: String::from("embeds[1].title"),
+                length: 257,
+                limit: 256,
+            }),
+            TheiaPluginEmbed::validate_all(&[Default::default(), long_title])
+        );
+
+        let empty_field = embed(r#"{"fields": [{"name": "a", "value": " "}]}"#);
+        assert_eq!(
+            Err(TheiaPluginMessageError::EmptyEmbedField(String::from(
+                "embeds[0].fields[0].value"
+            ))),
+            TheiaPluginEmbed::validate_all(&[empty_field])
+        );
+
+        let many: Vec<TheiaPluginEmbed> = (0..11).map(|_| Default::default()).collect();
+        assert_eq!(
+            Err(TheiaPluginMessageError::TooManyEmbeds {
+                count: 11,
+                limit: 10
+            }),
+            TheiaPluginEmbed::validate_all(&many)
+        );
+
+        let long: Vec<TheiaPluginEmbed> = (0..2)
+            .map(|_| TheiaPluginEmbed {
+                description: Some("x".repeat(3001)),
+                ..Default::default()
+            })
+            .collect();
+        assert_eq!(
+            Err(TheiaPluginMessageError::EmbedsTooLong {
+                length: 6002,
+                limit: 6000
+            }),
+            TheiaPluginEmbed::validate_all(&long)
+        );
+    }
+
+    #[test]
+    fn urls_and_timestamps() {
+        let valid = embed(
+            r#"{
+                "url": "https://example.com/a",
+                "thumbnail": "attachment://image.png",
+                "footer": {"text": "f", "icon_url": "http://example.com/i.png"},
+                "timestamp": "2021-06-01T12:00:00+02:00"
+            }"#,
+        );
+        assert_eq!(Ok(()), valid.validate(0));
+
+        let invalid = |json: &str, field: &str, expected: &'static str| {
+            assert_eq!(
+                Err(TheiaPluginMessageError::InvalidEmbedField {
+                    field: String::from(field),
+                    expected,
+                }),
+                embed(json).validate(1)
+            );
+        };
+
+        invalid(
+            r#"{"url": "attachment://image.png"}"#,
+            "embeds[1].url",
+            "an http(s) URL",
+        );
+        invalid(
+            r#"{"thumbnail": "example.com/image.png"}"#,
+            "embeds[1].thumbnail",
+            "an http(s) or attachment:// URL",
+        );
+        invalid(
+            r#"{"timestamp": "yesterday"}"#,
+            "embeds[1].timestamp",
+            "an RFC 3339 timestamp",
+        );
+    }
+}
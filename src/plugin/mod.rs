@@ -1,27 +1,51 @@
 //! The Theia plugin architecture.
 
-use crate::error::{TheiaError, TheiaPluginLoadError, TheiaPluginRunError};
+use crate::error::{
+    TheiaError, TheiaPluginLoadError, TheiaPluginMessageError, TheiaPluginRunError,
+};
 use crate::prelude::*;
 
 use ::serde_json::Value;
 use ::std::collections::HashMap;
 use ::std::fs;
+use ::std::future::Future;
 use ::std::path::{Path, PathBuf};
-use ::std::process::Stdio;
-use ::tokio::io::AsyncWriteExt;
+use ::std::process::{ExitStatus, Stdio};
+use ::std::time::Duration;
+use ::tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use ::tokio::process::{Child, ChildStdin};
 
 pub mod comms;
 use self::comms::*;
 
+pub mod attachment;
+pub mod embed;
+
 mod config;
 pub use self::config::*;
 
+mod owners;
+pub use self::owners::*;
+
+mod registry;
+pub use self::registry::*;
+
 /// A plugin.
 #[derive(Debug)]
 pub struct TheiaPlugin {
+    /// ID of this plugin instance, unique across all loaded plugins.
+    ///
+    /// This is the plugin's key in the bot configuration, or the plugin's
+    /// own name if it was loaded outside of a bot configuration.
+    pub instance_id: String,
+
     pub path: PathBuf,
     pub config: TheiaPluginConfig,
     pub cfgdata: HashMap<String, Value>,
+
+    /// Capabilities granted to this plugin instance in the bot
+    /// configuration.
+    pub grants: Vec<TheiaPluginCapability>,
 }
 
 impl TheiaPlugin {
@@ -47,10 +71,16 @@ impl TheiaPlugin {
             )
         })?;
 
+        config
+            .validate()
+            .map_err(|e| TheiaError::PluginLoad(String::from(path.to_string_lossy()), e))?;
+
         Ok(Self {
+            instance_id: config.name.clone(),
             path,
             config,
             cfgdata: Default::default(),
+            grants: Vec::new(),
         })
     }
 
@@ -65,6 +95,31 @@ impl<'a> TheiaPlugin {
         &self.config.name
     }
 
+    pub fn instance_id(&'a self) -> &'a str {
+        &self.instance_id
+    }
+
+    /// Checks that this plugin may use a capability, which it must both
+    /// declare in its manifest and be granted in the bot configuration.
+    pub fn check_capability(
+        &'a self,
+        capability: TheiaPluginCapability,
+    ) -> Result<(), TheiaPluginMessageError> {
+        if !self.config.capabilities.contains(&capability) {
+            return Err(TheiaPluginMessageError::CapabilityNotDeclared(
+                capability.name(),
+            ));
+        }
+
+        if !self.grants.contains(&capability) {
+            return Err(TheiaPluginMessageError::CapabilityNotGranted(
+                capability.name(),
+            ));
+        }
+
+        Ok(())
+    }
+
     pub fn command_config<C: AsRef<str>>(
         &'a self,
         cmd_name: C,
@@ -72,13 +127,39 @@ impl<'a> TheiaPlugin {
         self.config
             .commands
             .iter()
-            .find(|p| p.name == cmd_name.as_ref())
+            .find(|p| p.invoke_name() == cmd_name.as_ref())
     }
 
-    pub async fn invoke_raw(
+    /// Find the configuration for a command and a path of subcommands.
+    ///
+    /// Returns the chain of command configurations, starting with the
+    /// top-level command, for as much of the path as could be resolved.
+    pub fn command_chain<C: AsRef<str>, S: AsRef<str>>(
+        &'a self,
+        cmd_name: C,
+        subcommand: &[S],
+    ) -> Vec<&'a TheiaPluginCommandConfig> {
+        let mut chain: Vec<&'a TheiaPluginCommandConfig> = Vec::new();
+        if let Some(cmdcfg) = self.command_config(cmd_name) {
+            chain.push(cmdcfg);
+            for name in subcommand.iter() {
+                match chain[chain.len() - 1].subcommand(name) {
+                    Some(subcmd) => chain.push(subcmd),
+                    None => break,
+                }
+            }
+        }
+
+        chain
+    }
+
+    /// Spawn the plugin, writing a list of messages to its stdin.
+    ///
+    /// Stdin is left open, and returned alongside the child process.
+    async fn spawn(
         &'a self,
         msgs: &[TheiaPluginOutgoingMessage],
-    ) -> Result<Vec<TheiaPluginIncomingMessage>, TheiaError> {
+    ) -> Result<(Child, Option<ChildStdin>), TheiaError> {
         let to_write: String = {
             let mut to_write = Vec::new();
             for msg in msgs.iter() {
@@ -88,36 +169,95 @@ impl<'a> TheiaPlugin {
             format!("{}\n", to_write.join("\n"))
         };
 
-        let mut child = self
-            .config
-            .run
-            .as_tokio_command()
+        let mut command = self.config.run.as_tokio_command();
+        if let Some(invoke_id) = msgs.iter().find_map(|m| m.invoke_id()) {
+            command.env("THEIA_INVOKE_ID", invoke_id);
+        }
+
+        let mut child = command
             .current_dir(self.path.clone())
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
+            .kill_on_drop(true)
             .spawn()?;
 
         // write to child stdin
-        if let Some(mut stdin) = child.stdin.take() {
+        let mut stdin = child.stdin.take();
+        if let Some(stdin) = stdin.as_mut() {
             stdin.write_all(to_write.as_bytes()).await?;
         }
 
-        // grab output
-        let output = child.wait_with_output().await?;
-        if !output.status.success() {
-            if let Some(code) = output.status.code() {
+        Ok((child, stdin))
+    }
+
+    /// Run a future, failing with a timeout error if it takes longer than
+    /// the plugin's timeout.
+    async fn with_timeout<T, F: Future<Output = Result<T, TheiaError>>>(
+        &'a self,
+        future: F,
+    ) -> Result<T, TheiaError> {
+        match self.config.run_timeout() {
+            Some(secs) => ::tokio::time::timeout(Duration::from_secs(secs), future)
+                .await
+                .map_err(|_| {
+                    TheiaError::PluginRun(
+                        self.instance_id.clone(),
+                        TheiaPluginRunError::Timeout(secs),
+                    )
+                })?,
+
+            None => future.await,
+        }
+    }
+
+    /// Log and skip an error that only affects a single message from the
+    /// plugin, such as a direct message to a user not accepting them, rather
+    /// than failing the whole run.
+    fn skip_message_error(&'a self, why: TheiaError) -> Result<(), TheiaError> {
+        match why {
+            TheiaError::PluginMessage(_, TheiaPluginMessageError::DirectMessagesDisabled(_)) => {
+                warn!(
+                    "Skipping message from plugin {:?}: {}",
+                    self.instance_id, why
+                );
+                Ok(())
+            }
+
+            why => Err(why),
+        }
+    }
+
+    fn check_status(&'a self, status: ExitStatus) -> Result<(), TheiaError> {
+        if !status.success() {
+            if let Some(code) = status.code() {
                 return Err(TheiaError::PluginRun(
-                    self.config.name.clone(),
+                    self.instance_id.clone(),
                     TheiaPluginRunError::ExitStatus(code),
                 ));
             } else {
                 return Err(TheiaError::PluginRun(
-                    self.config.name.clone(),
+                    self.instance_id.clone(),
                     TheiaPluginRunError::Terminated,
                 ));
             }
         }
 
+        Ok(())
+    }
+
+    pub async fn invoke_raw(
+        &'a self,
+        msgs: &[TheiaPluginOutgoingMessage],
+    ) -> Result<Vec<TheiaPluginIncomingMessage>, TheiaError> {
+        let (child, stdin) = self.spawn(msgs).await?;
+        drop(stdin);
+
+        // grab output, killing the child if it runs for too long
+        let output = self
+            .with_timeout(async { Ok(child.wait_with_output().await?) })
+            .await?;
+        self.check_status(output.status)?;
+
         // convert output to string
         let output: Vec<String> = String::from_utf8(output.stdout)?
             .split("\n")
@@ -140,12 +280,96 @@ impl<'a> TheiaPlugin {
         ctx: &'ctx SerenityContext,
         msgs: &[TheiaPluginOutgoingMessage],
     ) -> Result<(), TheiaError> {
-        let mut responses = self.invoke_raw(&msgs).await?;
+        let mut invocation = TheiaPluginInvocation::new(self, msgs);
 
+        if self.config.interactive {
+            return self
+                .with_timeout(self.invoke_interactive(ctx, msgs, &mut invocation))
+                .await;
+        }
+
+        let mut responses = self.invoke_raw(&msgs).await?;
         for resp in responses.iter_mut() {
-            resp.process(&ctx).await?;
+            if let Err(why) = resp.process(&ctx, &mut invocation).await {
+                self.skip_message_error(why)?;
+            }
         }
 
         Ok(())
     }
+
+    /// Run the plugin with stdin kept open, processing each message from the
+    /// plugin as soon as it is received.
+    ///
+    /// The initial messages are followed by `EndOfInput`, and messages
+    /// carrying a nonce are acknowledged over stdin, with `MessageSent`,
+    /// `ActionDone` or `ActionFailed`.
+    async fn invoke_interactive(
+        &'a self,
+        ctx: &SerenityContext,
+        msgs: &[TheiaPluginOutgoingMessage],
+        invocation: &mut TheiaPluginInvocation<'_>,
+    ) -> Result<(), TheiaError> {
+        let (mut child, mut stdin) = self.spawn(msgs).await?;
+        if let Some(pipe) = stdin.as_mut() {
+            let end = ::serde_json::to_string(&TheiaPluginOutgoingMessage::EndOfInput {})?;
+            pipe.write_all(format!("{}\n", end).as_bytes()).await?;
+        }
+
+        let stdout = child.stdout.take().ok_or(TheiaError::UnknownError)?;
+        let mut lines = BufReader::new(stdout).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let response: TheiaPluginIncomingMessage = ::serde_json::from_str(line)?;
+            let result = response.process(ctx, invocation).await;
+            let nonce = match response.nonce() {
+                Some(nonce) => String::from(nonce),
+                None => {
+                    if let Err(why) = result {
+                        self.skip_message_error(why)?;
+                    }
+
+                    continue;
+                }
+            };
+
+            let ack = match result {
+                Ok(Some(sent)) => TheiaPluginOutgoingMessage::MessageSent {
+                    nonce,
+                    message_id: sent.id.to_string(),
+                    channel_id: sent.channel_id.to_string(),
+                },
+
+                Ok(None) => TheiaPluginOutgoingMessage::ActionDone { nonce },
+                Err(why) => {
+                    warn!(
+                        "Action {:?} from plugin {:?} failed: {}",
+                        nonce, self.instance_id, why
+                    );
+
+                    TheiaPluginOutgoingMessage::ActionFailed {
+                        nonce,
+                        error: format!("{}", why),
+                    }
+                }
+            };
+
+            // The plugin may have closed stdin if it doesn't want acks
+            if let Some(pipe) = stdin.as_mut() {
+                let ack = format!("{}\n", ::serde_json::to_string(&ack)?);
+                if pipe.write_all(ack.as_bytes()).await.is_err() {
+                    stdin = None;
+                }
+            }
+        }
+
+        drop(stdin);
+        let status = child.wait().await?;
+        self.check_status(status)
+    }
 }
@@ -0,0 +1,76 @@
+use ::std::collections::{HashMap, VecDeque};
+
+/// Record of which plugin instance sent each of the bot's recent messages.
+///
+/// Only the most recent messages are remembered, up to a fixed capacity.
+#[derive(Debug)]
+pub struct TheiaMessageOwners {
+    capacity: usize,
+
+    /// Map of message IDs to plugin instance IDs.
+    owners: HashMap<String, String>,
+
+    /// Message IDs, oldest first.
+    order: VecDeque<String>,
+}
+
+impl TheiaMessageOwners {
+    /// Default number of messages remembered.
+    pub const DEFAULT_CAPACITY: usize = 10000;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            owners: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Record that a plugin instance sent a message.
+    pub fn insert<M: AsRef<str>, P: AsRef<str>>(&mut self, message_id: M, instance_id: P) {
+        let message_id = String::from(message_id.as_ref());
+        if self
+            .owners
+            .insert(message_id.clone(), String::from(instance_id.as_ref()))
+            .is_none()
+        {
+            self.order.push_back(message_id);
+        }
+
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.owners.remove(&oldest);
+            }
+        }
+    }
+
+    /// The instance ID of the plugin that sent a message, if known.
+    pub fn owner<M: AsRef<str>>(&self, message_id: M) -> Option<&str> {
+        self.owners.get(message_id.as_ref()).map(String::as_str)
+    }
+}
+
+impl Default for TheiaMessageOwners {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_messages_forgotten() {
+        let mut owners = TheiaMessageOwners::new(2);
+        owners.insert("1", "a");
+        owners.insert("2", "b");
+        owners.insert("1", "a");
+        assert_eq!(Some("a"), owners.owner("1"));
+
+        owners.insert("3", "c");
+        assert_eq!(None, owners.owner("1"));
+        assert_eq!(Some("b"), owners.owner("2"));
+        assert_eq!(Some("c"), owners.owner("3"));
+    }
+}
@@ -0,0 +1,205 @@
+use super::TheiaPlugin;
+use crate::util::normalize::normalize_name;
+use ::std::collections::HashMap;
+
+/// A collision between two commands over a command name or alias.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TheiaCommandCollision {
+    /// The name or alias both commands wanted.
+    pub name: String,
+
+    /// Plugin instance ID and command name the name or alias resolves to.
+    pub winner: (String, String),
+
+    /// Plugin instance ID and command name that was shadowed.
+    pub shadowed: (String, String),
+}
+
+/// Mapping of command names and aliases to the plugin commands they invoke.
+#[derive(Debug, Default)]
+pub struct TheiaCommandRegistry {
+    /// Map of names and aliases to (plugin index, command index) pairs.
+    entries: HashMap<String, (usize, usize)>,
+
+    /// Collisions found while building this registry.
+    collisions: Vec<TheiaCommandCollision>,
+
+    /// Whether names are normalised before being registered or looked up.
+    case_insensitive: bool,
+}
+
+impl TheiaCommandRegistry {
+    /// Build a registry from a list of plugins.
+    ///
+    /// Collisions are resolved deterministically: every command name is
+    /// registered before any alias, so an alias never shadows another
+    /// command's name, and otherwise plugins earlier in the list win.
+    ///
+    /// If `case_insensitive` is set, names which are equal after
+    /// normalisation collide, and lookups are normalised.
+    pub fn build(plugins: &[TheiaPlugin], case_insensitive: bool) -> Self {
+        let mut registry = Self {
+            case_insensitive,
+            ..Default::default()
+        };
+
+        for aliases in [false, true] {
+            for (plugin_idx, plugin) in plugins.iter().enumerate() {
+                for (cmd_idx, cmdcfg) in plugin.config.commands.iter().enumerate() {
+                    let names = if aliases {
+                        cmdcfg.aliases.clone()
+                    } else {
+                        vec![cmdcfg.name.clone()]
+                    };
+
+                    for name in names.into_iter() {
+                        registry.register(plugins, name, (plugin_idx, cmd_idx));
+                    }
+                }
+            }
+        }
+
+        registry
+    }
+
+    fn register(&mut self, plugins: &[TheiaPlugin], name: String, target: (usize, usize)) {
+        let describe = |(plugin_idx, cmd_idx): (usize, usize)| {
+            let plugin = &plugins[plugin_idx];
+            (
+                plugin.instance_id.clone(),
+                plugin.config.commands[cmd_idx].name.clone(),
+            )
+        };
+
+        let name = self.key(name);
+        match self.entries.get(&name) {
+            Some(existing) if *existing == target => {}
+            Some(existing) => self.collisions.push(TheiaCommandCollision {
+                name,
+                winner: describe(*existing),
+                shadowed: describe(target),
+            }),
+
+            None => {
+                self.entries.insert(name, target);
+            }
+        }
+    }
+
+    fn key<T: AsRef<str>>(&self, name: T) -> String {
+        if self.case_insensitive {
+            normalize_name(name)
+        } else {
+            String::from(name.as_ref())
+        }
+    }
+
+    /// Look up the (plugin index, command index) pair for a name or alias.
+    pub fn lookup<T: AsRef<str>>(&self, name: T) -> Option<(usize, usize)> {
+        self.entries.get(&self.key(name)).copied()
+    }
+
+    /// Checks whether more than one command wanted the given name or alias.
+    pub fn is_ambiguous<T: AsRef<str>>(&self, name: T) -> bool {
+        let name = self.key(name);
+        self.collisions.iter().any(|c| c.name == name)
+    }
+
+    /// Collisions found while building this registry.
+    pub fn collisions(&self) -> &[TheiaCommandCollision] {
+        &self.collisions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(instance_id: &str, cfg: &str) -> TheiaPlugin {
+        TheiaPlugin {
+            instance_id: String::from(instance_id),
+            path: Default::default(),
+            config: ::toml::from_str(cfg).unwrap(),
+            cfgdata: Default::default(),
+            grants: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn collisions_resolved_deterministically() {
+        let first = plugin(
+            "first",
+            r#"
+                name = "shared"
+                run = "true"
+
+                [[command]]
+                name = "info"
+                aliases = ["about"]
+            "#,
+        );
+        let second = plugin(
+            "second",
+            r#"
+                name = "shared"
+                run = "true"
+
+                [[command]]
+                name = "about"
+
+                [[command]]
+                name = "info"
+            "#,
+        );
+
+        let registry = TheiaCommandRegistry::build(&[first, second], false);
+        assert_eq!(Some((0, 0)), registry.lookup("info"));
+        assert_eq!(Some((1, 0)), registry.lookup("about"));
+        assert!(registry.is_ambiguous("info"));
+        assert!(!registry.is_ambiguous("other"));
+        assert_eq!(None, registry.lookup("INFO"));
+        assert_eq!(
+            &[
+                TheiaCommandCollision {
+                    name: String::from("info"),
+                    winner: (String::from("first"), String::from("info")),
+                    shadowed: (String::from("second"), String::from("info")),
+                },
+                TheiaCommandCollision {
+                    name: String::from("about"),
+                    winner: (String::from("second"), String::from("about")),
+                    shadowed: (String::from("first"), String::from("info")),
+                },
+            ],
+            registry.collisions()
+        );
+    }
+
+    #[test]
+    fn case_insensitive_lookup() {
+        let upper = plugin(
+            "upper",
+            r#"
+                name = "upper"
+                run = "true"
+
+                [[command]]
+                name = "Info"
+            "#,
+        );
+        let lower = plugin(
+            "lower",
+            r#"
+                name = "lower"
+                run = "true"
+
+                [[command]]
+                name = "info"
+            "#,
+        );
+
+        let registry = TheiaCommandRegistry::build(&[upper, lower], true);
+        assert_eq!(Some((0, 0)), registry.lookup("INFO"));
+        assert!(registry.is_ambiguous("iNfO"));
+    }
+}
@@ -10,7 +10,8 @@ pub use crate::command::{invoke_command, CommandInvocation};
 pub use crate::config::TheiaConfig;
 pub use crate::discord::message::TheiaDiscordMessage;
 pub use crate::error::{TheiaError, TheiaPluginLoadError};
-pub use crate::parser::cmdhelp::parse_command_help;
+pub use crate::parser::cmdargs::{command_usage, parse_command_args};
+pub use crate::parser::cmdhelp::{parse_command_help, render_command_help};
 pub use crate::plugin::TheiaPlugin;
 pub use crate::typemap::*;
 pub use crate::util::runnable::RunnableCommand;
@@ -0,0 +1,90 @@
+//! Edit distance between strings, for fuzzy matching
+
+/// Levenshtein edit distance between two strings, in characters.
+pub fn edit_distance<A: AsRef<str>, B: AsRef<str>>(a: A, b: B) -> usize {
+    let a: Vec<char> = a.as_ref().chars().collect();
+    let b: Vec<char> = b.as_ref().chars().collect();
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur: Vec<usize> = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + if ca == cb { 0 } else { 1 };
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+
+        ::std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+/// Find the candidates closest to `input`, within `max_distance` edits.
+///
+/// Each candidate is a name along with any alternative spellings (such as
+/// aliases), and is matched by the closest of those. Returns up to `limit`
+/// candidate names, closest first.
+pub fn closest_matches<I: AsRef<str>>(
+    input: I,
+    candidates: &[(String, Vec<String>)],
+    max_distance: usize,
+    limit: usize,
+) -> Vec<String> {
+    let mut matches: Vec<(usize, &String)> = candidates
+        .iter()
+        .filter_map(|(name, alternatives)| {
+            ::std::iter::once(name)
+                .chain(alternatives.iter())
+                .map(|n| edit_distance(input.as_ref(), n))
+                .min()
+                .filter(|distance| *distance <= max_distance)
+                .map(|distance| (distance, name))
+        })
+        .collect();
+
+    matches.sort();
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, name)| name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(0, edit_distance("help", "help"));
+        assert_eq!(2, edit_distance("hlep", "help"));
+        assert_eq!(1, edit_distance("hel", "help"));
+        assert_eq!(4, edit_distance("", "help"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+    }
+
+    #[test]
+    fn closest() {
+        let candidates = vec![
+            (String::from("help"), vec![]),
+            (String::from("info"), vec![String::from("about")]),
+            (String::from("hello"), vec![]),
+        ];
+
+        assert_eq!(
+            vec![String::from("help")],
+            closest_matches("hlep", &candidates, 2, 3)
+        );
+        assert_eq!(
+            vec![String::from("hello"), String::from("help")],
+            closest_matches("helo", &candidates, 2, 3)
+        );
+        assert_eq!(
+            vec![String::from("info")],
+            closest_matches("abot", &candidates, 2, 3)
+        );
+        assert!(closest_matches("xyzzy", &candidates, 2, 3).is_empty());
+    }
+}
@@ -1,3 +1,5 @@
 //! Utility methods & structures
 
+pub mod distance;
+pub mod normalize;
 pub mod runnable;
@@ -0,0 +1,52 @@
+//! Normalisation of names for case-insensitive matching
+
+use ::unicode_normalization::UnicodeNormalization;
+
+/// Normalise a name for case-insensitive matching.
+///
+/// The name is NFKC-normalised, so that compatibility forms (such as
+/// full-width letters) match their plain equivalents, and then lowercased.
+/// Lowercasing uses the locale-independent Unicode mappings, and the result
+/// is normalised again.
+///
+/// This doesn't detect confusable characters from different scripts, such
+/// as Cyrillic `а` and Latin `a`, which still don't match.
+pub fn normalize_name<T: AsRef<str>>(name: T) -> String {
+    let lowered = name.as_ref().nfkc().collect::<String>().to_lowercase();
+    lowered.nfkc().collect()
+}
+
+/// Find the length in bytes of the start of `input` that matches `prefix`
+/// after normalisation, if any.
+pub fn normalized_prefix_len<I: AsRef<str>, P: AsRef<str>>(input: I, prefix: P) -> Option<usize> {
+    let input = input.as_ref();
+    let prefix = normalize_name(prefix);
+    let max_chars = prefix.chars().count() * 2 + 1;
+
+    input
+        .char_indices()
+        .map(|(idx, c)| idx + c.len_utf8())
+        .take(max_chars)
+        .find(|&end| normalize_name(&input[..end]) == prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_case_and_compatibility_forms() {
+        assert_eq!("help", normalize_name("Help"));
+        assert_eq!("help", normalize_name("ＨＥＬＰ"));
+        assert_eq!("fish", normalize_name("ﬁsh"));
+        assert_ne!(normalize_name("i"), normalize_name("İ"));
+    }
+
+    #[test]
+    fn prefix_lengths() {
+        assert_eq!(Some(2), normalized_prefix_len("T;help", "t;"));
+        assert_eq!(Some(6), normalized_prefix_len("Ｔ；help", "t;"));
+        assert_eq!(None, normalized_prefix_len("x;help", "t;"));
+        assert_eq!(None, normalized_prefix_len("", "t;"));
+    }
+}